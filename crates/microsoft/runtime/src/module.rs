@@ -7,9 +7,8 @@ use windows::core::{HSTRING, PCSTR, PCWSTR};
 use windows::Win32::Foundation::{HMODULE, MAX_PATH};
 use windows::Win32::System::Threading::GetCurrentProcess;
 use windows::Win32::System::LibraryLoader::{GetModuleHandleW, GetProcAddress};
-use windows::Win32::System::SystemServices::IMAGE_DOS_HEADER;
 use windows::Win32::System::Diagnostics::Debug::ImageNtHeader;
-use windows::Win32::System::Diagnostics::Debug::IMAGE_NT_HEADERS64;
+use windows::Win32::System::Diagnostics::Debug::IMAGE_FILE_HEADER;
 use windows::Win32::System::Diagnostics::Debug::IMAGE_SECTION_HEADER;
 use windows::Win32::System::ProcessStatus::{EnumProcessModules, GetModuleBaseNameA};
 
@@ -118,49 +117,83 @@ pub fn get_module_symbol(module: impl AsRef<str>, symbol: impl AsRef<str>) -> Re
 
 /// Retrieves the address range of a section in a module.
 pub fn get_module_section_range(module: impl AsRef<str>, specified_section: impl AsRef<str>) -> Result<ops::Range<usize>, LookupError> {
-    let module_base = get_module_handle(module)?;
+    let specified_section = specified_section.as_ref();
+
+    get_module_sections(module)?
+        .find(|s| s.name == specified_section)
+        .map(|s| s.memory_range)
+        .ok_or(LookupError::SectionNotFound)
+}
+
+/// Enumerates the sections of a module by its name.
+pub fn get_module_sections(module: impl AsRef<str>) -> Result<SectionIter, LookupError> {
+    get_module_handle(module).map(get_module_sections_by_base)
+}
 
+/// WARNING: this function does not perform any sanity-checking on the input.
+/// Enumerates the sections of a module by the modules base.
+pub fn get_module_sections_by_base(module_base: usize) -> SectionIter {
     let image_nt_header = unsafe { ImageNtHeader(module_base as *const ffi::c_void) };
-    let num_sections = unsafe { (*image_nt_header).FileHeader.NumberOfSections as u32 };
-    let number_of_rva_and_sizes =
-        unsafe { (*image_nt_header).OptionalHeader.NumberOfRvaAndSizes };
+    let remaining = unsafe { (*image_nt_header).FileHeader.NumberOfSections as u32 };
+    let optional_header_size = unsafe { (*image_nt_header).FileHeader.SizeOfOptionalHeader as usize };
 
-    // The sections should be right after the Image NT header.
-    // That means we'll have to parse the DOS header to figure out how when the optional header ends.
-    let dos_header = module_base as *const IMAGE_DOS_HEADER;
-    let nt_header_base = module_base + unsafe { (*dos_header).e_lfanew as usize };
+    // The section headers come right after the optional header, which varies in size depending on
+    // the amount of data directories.
+    let current = image_nt_header as usize
+        + mem::size_of::<u32>()
+        + mem::size_of::<IMAGE_FILE_HEADER>()
+        + optional_header_size;
 
-    // The IMAGE_NT_HEADERS64 structure assumes 16 data directory entries. This is not a given so we subtract the difference.
-    let section_base = nt_header_base
-        + mem::size_of::<IMAGE_NT_HEADERS64>()
-        + ((number_of_rva_and_sizes - 16) * 8) as usize;
+    SectionIter { module_base, current, remaining }
+}
 
-    let specified_section = specified_section.as_ref();
-    unsafe {
-        let mut current_section_header = section_base;
-        let section_header_size = mem::size_of::<IMAGE_SECTION_HEADER>();
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub name: String,
+    pub memory_range: ops::Range<usize>,
+    /// The `IMAGE_SCN_*` flags from the section header.
+    pub characteristics: u32,
+}
 
-        for _ in 0..num_sections {
-            let section_header = current_section_header as *const IMAGE_SECTION_HEADER;
+/// Walks the section headers of a loaded module.
+#[derive(Debug)]
+pub struct SectionIter {
+    module_base: usize,
+    current: usize,
+    remaining: u32,
+}
 
-            let section_name = PCSTR::from_raw((*section_header).Name.as_ptr())
-                .to_string()
-                .expect("Could not get name from section");
+impl Iterator for SectionIter {
+    type Item = Section;
 
-            if section_name == specified_section {
-                let section_size = (*section_header).Misc.VirtualSize;
-                let section_va = (*section_header).VirtualAddress;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let section_header = self.current as *const IMAGE_SECTION_HEADER;
+        self.current += mem::size_of::<IMAGE_SECTION_HEADER>();
+        self.remaining -= 1;
 
-                let start = module_base + section_va as usize;
-                let end = module_base + section_va as usize + section_size as usize;
-                return Ok(ops::Range { start, end });
-            }
+        unsafe {
+            // Names that take up all 8 bytes aren't null-terminated
+            let name = &(*section_header).Name;
+            let name_length = name.iter().position(|c| *c == 0).unwrap_or(name.len());
 
-            current_section_header += section_header_size;
+            let start = self.module_base + (*section_header).VirtualAddress as usize;
+            let end = start + (*section_header).Misc.VirtualSize as usize;
+
+            Some(Section {
+                name: String::from_utf8_lossy(&name[..name_length]).to_string(),
+                memory_range: ops::Range { start, end },
+                characteristics: (*section_header).Characteristics.0,
+            })
         }
     }
 
-    Err(LookupError::SectionNotFound)
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining as usize, Some(self.remaining as usize))
+    }
 }
 
 #[derive(Debug)]
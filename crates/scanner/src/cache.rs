@@ -0,0 +1,205 @@
+use std::io;
+use std::fs;
+use std::path::Path;
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::pattern::Pattern;
+use crate::pe::{nt_headers, read_u16, read_u32};
+
+/// Identifies a build of a module. Any update to the binary changes at least one of these.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModuleFingerprint {
+    /// `TimeDateStamp` from the file header.
+    pub timestamp: u32,
+    /// `SizeOfImage` from the optional header.
+    pub size_of_image: u32,
+    /// FNV-1a hash of the section table.
+    pub section_table_hash: u64,
+}
+
+impl ModuleFingerprint {
+    /// Reads the fingerprint from the PE headers at the start of `image`. This works on both
+    /// mapped modules and files on disk as the headers are the same for both.
+    pub fn from_headers(image: &[u8]) -> Option<Self> {
+        let file_header = nt_headers(image)? + 4;
+        let number_of_sections = read_u16(image, file_header + 2)? as usize;
+        let timestamp = read_u32(image, file_header + 4)?;
+        let size_of_optional_header = read_u16(image, file_header + 16)? as usize;
+
+        // SizeOfImage sits at the same offset for both PE32 and PE32+
+        let optional_header = file_header + 20;
+        let size_of_image = read_u32(image, optional_header + 56)?;
+
+        let section_table = optional_header + size_of_optional_header;
+        let section_table = image.get(section_table..section_table + number_of_sections * 40)?;
+
+        Some(Self { timestamp, size_of_image, section_table_hash: fnv1a(section_table) })
+    }
+
+    /// Reads the fingerprint of a module loaded in the current process.
+    #[cfg(windows)]
+    pub fn from_module(module: &str) -> Option<Self> {
+        let (_, headers) = crate::pe::loaded_module_headers(module)?;
+        Self::from_headers(headers)
+    }
+}
+
+/// Used over std's hashers because the hash is persisted and those aren't stable across releases.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// RVAs of earlier matches per module. Entries are only handed out for the exact build of the
+/// module they were found in, inserting an entry for a different build drops everything that was
+/// cached for the module. Callers are expected to check the pattern at the RVA before trusting it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanCache {
+    modules: HashMap<String, CachedModule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CachedModule {
+    fingerprint: ModuleFingerprint,
+    entries: HashMap<String, CachedEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CachedEntry {
+    /// The pattern the RVA was found with, changing the pattern invalidates the entry.
+    pattern: String,
+    rva: usize,
+}
+
+impl ScanCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the cache from a JSON file. A file that doesn't exist yet results in an empty cache.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        fs::write(path, contents)
+    }
+
+    /// The RVA cached for `name` in `module`, if the module hasn't changed and the entry was made
+    /// with the same pattern.
+    pub fn get(
+        &self,
+        module: &str,
+        fingerprint: &ModuleFingerprint,
+        name: &str,
+        pattern: &Pattern,
+    ) -> Option<usize> {
+        let module = self.modules.get(module)
+            .filter(|m| m.fingerprint == *fingerprint)?;
+
+        module.entries.get(name)
+            .filter(|e| e.pattern == pattern.to_string())
+            .map(|e| e.rva)
+    }
+
+    pub fn insert(
+        &mut self,
+        module: &str,
+        fingerprint: &ModuleFingerprint,
+        name: &str,
+        pattern: &Pattern,
+        rva: usize,
+    ) {
+        let module = self.modules.entry(module.to_string())
+            .or_insert_with(|| CachedModule { fingerprint: *fingerprint, entries: HashMap::new() });
+
+        if module.fingerprint != *fingerprint {
+            module.fingerprint = *fingerprint;
+            module.entries.clear();
+        }
+
+        module.entries.insert(name.to_string(), CachedEntry { pattern: pattern.to_string(), rva });
+    }
+
+    /// Drops every entry of the module.
+    pub fn invalidate(&mut self, module: &str) {
+        self.modules.remove(module);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pattern::Pattern;
+    use crate::cache::{ModuleFingerprint, ScanCache};
+    use crate::pe::fixtures::TestImage;
+
+    #[test]
+    fn fingerprint_reads_pe_headers() {
+        let headers = |text_rva| TestImage::new(0x400)
+            .timestamp(0x5F000000)
+            .size_of_image(0x2000)
+            .section(".text", text_rva, 0x1000)
+            .into_bytes();
+
+        let image = headers(0x1000);
+        let fingerprint = ModuleFingerprint::from_headers(&image).unwrap();
+
+        assert_eq!(fingerprint.timestamp, 0x5F000000);
+        assert_eq!(fingerprint.size_of_image, 0x2000);
+
+        let moved_section = ModuleFingerprint::from_headers(&headers(0x1001)).unwrap();
+        assert_ne!(moved_section, fingerprint);
+
+        assert_eq!(ModuleFingerprint::from_headers(&image[..0x100]), None);
+        assert_eq!(ModuleFingerprint::from_headers(&[0; 0x400]), None);
+    }
+
+    #[test]
+    fn cache_is_invalidated_by_module_and_pattern_changes() {
+        let old = ModuleFingerprint { timestamp: 1, size_of_image: 0x2000, section_table_hash: 2 };
+        let new = ModuleFingerprint { timestamp: 3, ..old };
+        let pattern = Pattern::from_byte_pattern("48 8B 05").unwrap();
+        let other_pattern = Pattern::from_byte_pattern("48 8B 0D").unwrap();
+
+        let mut cache = ScanCache::new();
+        cache.insert("game.exe", &old, "a", &pattern, 0x1234);
+        cache.insert("game.exe", &old, "b", &pattern, 0x5678);
+
+        assert_eq!(cache.get("game.exe", &old, "a", &pattern), Some(0x1234));
+        assert_eq!(cache.get("game.exe", &old, "a", &other_pattern), None);
+        assert_eq!(cache.get("game.exe", &new, "a", &pattern), None);
+        assert_eq!(cache.get("other.dll", &old, "a", &pattern), None);
+
+        cache.insert("game.exe", &new, "a", &pattern, 0x4321);
+        assert_eq!(cache.get("game.exe", &new, "a", &pattern), Some(0x4321));
+        assert_eq!(cache.get("game.exe", &new, "b", &pattern), None);
+    }
+
+    #[test]
+    fn cache_persists_to_disk() {
+        let path = std::env::temp_dir().join(format!("broadsword-cache-{}.json", std::process::id()));
+        let fingerprint = ModuleFingerprint { timestamp: 1, size_of_image: 0x2000, section_table_hash: 2 };
+        let pattern = Pattern::from_byte_pattern("48 8B 05 [?? ?? ?? ??]").unwrap();
+
+        assert_eq!(ScanCache::load(&path).unwrap(), ScanCache::new());
+
+        let mut cache = ScanCache::new();
+        cache.insert("game.exe", &fingerprint, "a", &pattern, 0x1234);
+        cache.save(&path).unwrap();
+
+        let loaded = ScanCache::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), cache);
+    }
+}
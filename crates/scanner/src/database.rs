@@ -0,0 +1,711 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+use std::error::Error;
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::pattern::Pattern;
+use crate::cache::{ModuleFingerprint, ScanCache};
+use crate::scanner::{MatchMode, ScanResult};
+use crate::scanner::result::build_result;
+use crate::scanner::threaded::{scan_all, ThreadedScanError};
+
+/// A set of signatures loaded from a TOML or JSON file:
+///
+/// ```toml
+/// [[signatures]]
+/// name = "world_chr_man"
+/// pattern = "48 8B 05 [?? ?? ?? ??] 48 85 C0 74 0F 48 39 88"
+/// module = "eldenring.exe"
+/// section = ".text"
+/// steps = [{ rip_relative = { capture = 0 } }, "deref"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct SignatureDatabase {
+    #[serde(default)]
+    pub signatures: Vec<Signature>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Signature {
+    pub name: String,
+    pub pattern: Pattern,
+    pub module: String,
+    #[serde(default = "default_section")]
+    pub section: String,
+    /// Applied in order to the location of the match to get to the address of the signature.
+    #[serde(default)]
+    pub steps: Vec<Step>,
+}
+
+fn default_section() -> String {
+    ".text".to_string()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Step {
+    /// Adds a signed amount of bytes to the address.
+    Offset(isize),
+    /// Resolves the RIP-relative 32-bit displacement in the capture. The displacement is taken to
+    /// be followed by `immediate_size` bytes of immediate before the next instruction starts.
+    RipRelative {
+        #[serde(default)]
+        capture: CaptureRef,
+        #[serde(default)]
+        immediate_size: usize,
+    },
+    /// Reads the pointer at the address.
+    Deref,
+}
+
+/// Refers to a capture group by its index or its name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CaptureRef {
+    Index(usize),
+    Name(String),
+}
+
+impl Default for CaptureRef {
+    fn default() -> Self {
+        Self::Index(0)
+    }
+}
+
+impl fmt::Display for CaptureRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Index(index) => write!(f, "{index}"),
+            Self::Name(name) => write!(f, "`{name}`"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DatabaseError {
+    Toml(toml::de::Error),
+    Json(serde_json::Error),
+    /// Two signatures in the database share the same name.
+    DuplicateName(String),
+}
+
+impl fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Toml(e) => write!(f, "could not parse signature database: {e}"),
+            Self::Json(e) => write!(f, "could not parse signature database: {e}"),
+            Self::DuplicateName(name) => write!(f, "signature `{name}` is defined more than once"),
+        }
+    }
+}
+
+impl Error for DatabaseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Toml(e) => Some(e),
+            Self::Json(e) => Some(e),
+            Self::DuplicateName(_) => None,
+        }
+    }
+}
+
+impl SignatureDatabase {
+    pub fn from_toml(input: &str) -> Result<Self, DatabaseError> {
+        toml::from_str::<Self>(input)
+            .map_err(DatabaseError::Toml)?
+            .validated()
+    }
+
+    pub fn from_json(input: &str) -> Result<Self, DatabaseError> {
+        serde_json::from_str::<Self>(input)
+            .map_err(DatabaseError::Json)?
+            .validated()
+    }
+
+    fn validated(self) -> Result<Self, DatabaseError> {
+        let mut names = HashSet::new();
+        for signature in self.signatures.iter() {
+            if !names.insert(signature.name.as_str()) {
+                return Err(DatabaseError::DuplicateName(signature.name.clone()));
+            }
+        }
+
+        Ok(self)
+    }
+
+    /// Scans for every signature and applies its steps. Signatures are only resolved if they match
+    /// exactly once, every signature ends up in the report either way.
+    pub fn resolve(&self, memory: &impl SignatureMemory) -> Resolution {
+        self.resolve_with(memory, None)
+    }
+
+    /// Same as `resolve` but checks the cache before scanning. A cached location is only used if
+    /// the module hasn't changed and the pattern still matches there. Signatures that had to be
+    /// scanned for are added to the cache when they match exactly once.
+    pub fn resolve_cached(&self, memory: &impl SignatureMemory, cache: &mut ScanCache) -> Resolution {
+        self.resolve_with(memory, Some(cache))
+    }
+
+    fn resolve_with(&self, memory: &impl SignatureMemory, mut cache: Option<&mut ScanCache>) -> Resolution {
+        let mut addresses = HashMap::new();
+        let mut entries = Vec::new();
+
+        for signature in self.signatures.iter() {
+            let start = Instant::now();
+            let (status, hits) = resolve_signature(signature, memory, cache.as_deref_mut());
+
+            if let SignatureStatus::Found(address) = status {
+                addresses.insert(signature.name.clone(), address);
+            }
+
+            entries.push(ReportEntry {
+                name: signature.name.clone(),
+                status,
+                hits,
+                duration: start.elapsed(),
+            });
+        }
+
+        Resolution { addresses, report: ResolutionReport { entries } }
+    }
+}
+
+/// Where the resolver gets its bytes from. Implemented for the current process by `ProcessMemory`
+/// and for plain buffers by `BufferMemory`.
+pub trait SignatureMemory {
+    /// The bytes of a section in a module, along with the address of the first byte.
+    fn section(&self, module: &str, section: &str) -> Option<(usize, &[u8])>;
+
+    /// Reads a pointer at the address, `None` if it can't be read.
+    fn read_pointer(&self, address: usize) -> Option<usize>;
+
+    /// The base address of a module along with its PE headers, used to tell builds of the module
+    /// apart when caching. Modules without headers aren't cached.
+    fn module_headers(&self, _module: &str) -> Option<(usize, &[u8])> {
+        None
+    }
+}
+
+/// Resolves a single signature, returns its status and how it was looked for.
+fn resolve_signature(
+    signature: &Signature,
+    memory: &impl SignatureMemory,
+    cache: Option<&mut ScanCache>,
+) -> (SignatureStatus, Hits) {
+    let Some((base, bytes)) = memory.section(&signature.module, &signature.section) else {
+        return (SignatureStatus::Failed(ResolveError::SectionNotFound), Hits::NotScanned);
+    };
+
+    let mut cache = cache.zip(
+        memory.module_headers(&signature.module)
+            .and_then(|(module_base, headers)| Some((module_base, ModuleFingerprint::from_headers(headers)?)))
+    );
+
+    let cached = cache.as_ref()
+        .and_then(|(cache, (module_base, fingerprint))| {
+            let rva = cache.get(&signature.module, fingerprint, &signature.name, &signature.pattern)?;
+            let location = (module_base + rva).checked_sub(base)
+                .filter(|l| *l < bytes.len())?;
+
+            signature.pattern.matches(&bytes[location..])
+                .then(|| build_result(bytes, &signature.pattern, location))
+        });
+
+    let is_cached = cached.is_some();
+    let results = match cached {
+        Some(result) => vec![result],
+        // Overlapping matches count too, otherwise a pattern that overlaps itself passes for unique
+        None => match scan_all(bytes, &signature.pattern, MatchMode::Overlapping, None) {
+            Ok(results) => results,
+            Err(e) => return (SignatureStatus::Failed(ResolveError::Scan(e)), Hits::NotScanned),
+        },
+    };
+
+    if let (false, [result]) = (is_cached, results.as_slice()) {
+        if let Some((cache, (module_base, fingerprint))) = cache.as_mut() {
+            if let Some(rva) = (base + result.location).checked_sub(*module_base) {
+                cache.insert(&signature.module, fingerprint, &signature.name, &signature.pattern, rva);
+            }
+        }
+    }
+
+    let status = match results.as_slice() {
+        [] => SignatureStatus::Missing,
+        [result] => match apply_steps(signature, result, base, memory) {
+            Ok(address) => SignatureStatus::Found(address),
+            Err(e) => SignatureStatus::Failed(e),
+        },
+        _ => SignatureStatus::Ambiguous(results.iter().map(|r| base + r.location).collect()),
+    };
+
+    let hits = match is_cached {
+        true => Hits::Cached,
+        false => Hits::Scanned(results.len()),
+    };
+
+    (status, hits)
+}
+
+fn apply_steps(
+    signature: &Signature,
+    result: &ScanResult,
+    base: usize,
+    memory: &impl SignatureMemory,
+) -> Result<usize, ResolveError> {
+    let mut address = base + result.location;
+
+    for step in signature.steps.iter() {
+        address = match step {
+            Step::Offset(offset) => address.checked_add_signed(*offset)
+                .ok_or(ResolveError::Overflow)?,
+            Step::RipRelative { capture, immediate_size } => {
+                let index = match capture {
+                    CaptureRef::Index(index) => *index,
+                    CaptureRef::Name(name) => signature.pattern.capture_index(name)
+                        .map_err(|_| ResolveError::UnknownCapture(capture.clone()))?,
+                };
+
+                let capture = result.capture(index)
+                    .ok_or_else(|| ResolveError::UnknownCapture(capture.clone()))?;
+                let displacement = capture.as_i32()
+                    .map_err(|_| ResolveError::InvalidDisplacement(index))?;
+
+                (base + capture.location + 4 + immediate_size)
+                    .checked_add_signed(displacement as isize)
+                    .ok_or(ResolveError::Overflow)?
+            },
+            Step::Deref => memory.read_pointer(address)
+                .ok_or(ResolveError::UnreadablePointer(address))?,
+        };
+    }
+
+    Ok(address)
+}
+
+/// The addresses of the signatures that were found along with a report on all of them.
+#[derive(Debug)]
+pub struct Resolution {
+    pub addresses: HashMap<String, usize>,
+    pub report: ResolutionReport,
+}
+
+impl Resolution {
+    pub fn get(&self, name: &str) -> Option<usize> {
+        self.addresses.get(name).copied()
+    }
+}
+
+#[derive(Debug)]
+pub struct ResolutionReport {
+    pub entries: Vec<ReportEntry>,
+}
+
+#[derive(Debug)]
+pub struct ReportEntry {
+    pub name: String,
+    pub status: SignatureStatus,
+    pub hits: Hits,
+    /// Time spent on scanning and applying the steps.
+    pub duration: Duration,
+}
+
+/// How a signature was looked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hits {
+    /// The section was scanned, holds the amount of times the pattern matched.
+    Scanned(usize),
+    /// The pattern was checked at the location from the cache instead of being scanned for.
+    Cached,
+    /// The section couldn't be found or scanned.
+    NotScanned,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SignatureStatus {
+    /// Matched exactly once, holds the address after applying the steps.
+    Found(usize),
+    Missing,
+    /// Matched more than once, holds the locations of the matches.
+    Ambiguous(Vec<usize>),
+    /// Matched once but couldn't be resolved, or couldn't be scanned for.
+    Failed(ResolveError),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ResolveError {
+    SectionNotFound,
+    Scan(ThreadedScanError),
+    /// A step refers to a capture the pattern doesn't have.
+    UnknownCapture(CaptureRef),
+    /// The capture at the index isn't 4 bytes wide.
+    InvalidDisplacement(usize),
+    /// A deref step ran into an address that can't be read.
+    UnreadablePointer(usize),
+    Overflow,
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SectionNotFound => write!(f, "module or section not found"),
+            Self::Scan(e) => write!(f, "{e}"),
+            Self::UnknownCapture(capture) => write!(f, "pattern has no capture {capture}"),
+            Self::InvalidDisplacement(index) => write!(f, "capture {index} is not 4 bytes wide"),
+            Self::UnreadablePointer(address) => write!(f, "cannot read pointer at {address:#x}"),
+            Self::Overflow => write!(f, "address overflowed"),
+        }
+    }
+}
+
+impl Error for ResolveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Scan(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl ResolutionReport {
+    pub fn found(&self) -> usize {
+        self.entries.iter()
+            .filter(|e| matches!(e.status, SignatureStatus::Found(_)))
+            .count()
+    }
+
+    /// `true` if every signature in the database was resolved.
+    pub fn is_complete(&self) -> bool {
+        self.found() == self.entries.len()
+    }
+}
+
+/// One line per signature, followed by a summary.
+impl fmt::Display for ResolutionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut total = Duration::ZERO;
+
+        for entry in self.entries.iter() {
+            total += entry.duration;
+
+            write!(f, "{}: ", entry.name)?;
+            match &entry.status {
+                SignatureStatus::Found(address) => write!(f, "found at {address:#x}")?,
+                SignatureStatus::Missing => write!(f, "missing")?,
+                SignatureStatus::Ambiguous(locations) => {
+                    let locations = locations.iter()
+                        .map(|l| format!("{l:#x}"))
+                        .collect::<Vec<_>>();
+
+                    write!(f, "ambiguous, matches at {}", locations.join(", "))?;
+                },
+                SignatureStatus::Failed(e) => write!(f, "failed, {e}")?,
+            }
+
+            match entry.hits {
+                Hits::Scanned(hits) => writeln!(f, " ({hits} hits, {:?})", entry.duration)?,
+                Hits::Cached => writeln!(f, " (cached, {:?})", entry.duration)?,
+                Hits::NotScanned => writeln!(f, " (not scanned, {:?})", entry.duration)?,
+            }
+        }
+
+        write!(f, "{} of {} signatures found in {total:?}", self.found(), self.entries.len())
+    }
+}
+
+/// Sections as plain buffers, mostly useful for resolving signatures against files on disk and for
+/// tests. Pointers are read from any of the sections.
+#[derive(Debug, Default)]
+pub struct BufferMemory {
+    sections: HashMap<(String, String), (usize, Vec<u8>)>,
+    headers: HashMap<String, (usize, Vec<u8>)>,
+}
+
+impl BufferMemory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, module: &str, section: &str, base: usize, bytes: Vec<u8>) {
+        self.sections.insert((module.to_string(), section.to_string()), (base, bytes));
+    }
+
+    /// Sets the base address and PE headers of a module, this makes the module cacheable.
+    pub fn insert_headers(&mut self, module: &str, base: usize, headers: Vec<u8>) {
+        self.headers.insert(module.to_string(), (base, headers));
+    }
+}
+
+impl SignatureMemory for BufferMemory {
+    fn section(&self, module: &str, section: &str) -> Option<(usize, &[u8])> {
+        self.sections.get(&(module.to_string(), section.to_string()))
+            .map(|(base, bytes)| (*base, bytes.as_slice()))
+    }
+
+    fn read_pointer(&self, address: usize) -> Option<usize> {
+        const SIZE: usize = std::mem::size_of::<usize>();
+
+        self.sections.values()
+            .find_map(|(base, bytes)| {
+                let offset = address.checked_sub(*base)?;
+                bytes.get(offset..offset.checked_add(SIZE)?)
+            })
+            .map(|b| usize::from_le_bytes(b.try_into().unwrap()))
+    }
+
+    fn module_headers(&self, module: &str) -> Option<(usize, &[u8])> {
+        self.headers.get(module)
+            .map(|(base, headers)| (*base, headers.as_slice()))
+    }
+}
+
+/// The modules loaded in the current process, sections are looked up with
+/// `get_module_section_range`.
+#[cfg(windows)]
+pub struct ProcessMemory;
+
+#[cfg(windows)]
+impl SignatureMemory for ProcessMemory {
+    fn section(&self, module: &str, section: &str) -> Option<(usize, &[u8])> {
+        let range = broadsword_microsoft_runtime::module::get_module_section_range(module, section).ok()?;
+
+        // SAFETY: the section is mapped for as long as the module is loaded
+        let bytes = unsafe { std::slice::from_raw_parts(range.start as *const u8, range.len()) };
+        Some((range.start, bytes))
+    }
+
+    fn read_pointer(&self, address: usize) -> Option<usize> {
+        if !broadsword_microsoft_runtime::pointer::is_valid_pointer(address) {
+            return None;
+        }
+
+        Some(unsafe { *(address as *const usize) })
+    }
+
+    fn module_headers(&self, module: &str) -> Option<(usize, &[u8])> {
+        crate::pe::loaded_module_headers(module)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cache::ScanCache;
+    use crate::database::{BufferMemory, CaptureRef, DatabaseError, Hits, ResolveError};
+    use crate::database::{SignatureDatabase, SignatureStatus, Step};
+    use crate::pe::fixtures::{Code, TestImage, DATA_BASE, IMAGE_BASE, TEXT_BASE};
+
+    /// The pointer stored in `.data`.
+    const GLOBAL: usize = DATA_BASE + 0x40;
+
+    /// `.text` with the global loaded at `offset`, and a nop that shows up twice.
+    fn text(offset: usize) -> Vec<u8> {
+        Code::new(TEXT_BASE)
+            // mov rax, [data]; test rax, rax
+            .at(offset)
+            .rip_relative(&[0x48, 0x8B, 0x05], DATA_BASE, &[])
+            .bytes(&[0x48, 0x85, 0xC0])
+            .at(0x40)
+            .bytes(&[0x0F, 0x1F, 0x40, 0x00])
+            .at(0x50)
+            .bytes(&[0x0F, 0x1F, 0x40, 0x00])
+            .at(0x100)
+            .into_bytes()
+    }
+
+    fn headers(timestamp: u32) -> Vec<u8> {
+        TestImage::new(0x400)
+            .timestamp(timestamp)
+            .size_of_image(0x6000)
+            .section(".text", 0x1000, 0x100)
+            .section(".data", 0x5000, 0x8)
+            .into_bytes()
+    }
+
+    fn memory() -> BufferMemory {
+        let mut memory = BufferMemory::new();
+        memory.insert("game.exe", ".text", TEXT_BASE, text(0x10));
+        memory.insert("game.exe", ".data", DATA_BASE, GLOBAL.to_le_bytes().to_vec());
+        memory
+    }
+
+    #[test]
+    fn database_loads_from_toml_and_json() {
+        let toml = SignatureDatabase::from_toml(r#"
+            [[signatures]]
+            name = "global"
+            pattern = "48 8B 05 [displacement: ?? ?? ?? ??] 48 85 C0"
+            module = "game.exe"
+            steps = [{ rip_relative = { capture = "displacement" } }, "deref", { offset = -16 }]
+        "#).unwrap();
+
+        let json = SignatureDatabase::from_json(r#"{
+            "signatures": [{
+                "name": "global",
+                "pattern": "48 8B 05 [displacement: ?? ?? ?? ??] 48 85 C0",
+                "module": "game.exe",
+                "section": ".text",
+                "steps": [{ "rip_relative": { "capture": "displacement" } }, "deref", { "offset": -16 }]
+            }]
+        }"#).unwrap();
+
+        assert_eq!(toml, json);
+        assert_eq!(toml.signatures[0].section, ".text");
+        assert_eq!(toml.signatures[0].steps, vec![
+            Step::RipRelative { capture: CaptureRef::Name("displacement".to_string()), immediate_size: 0 },
+            Step::Deref,
+            Step::Offset(-16),
+        ]);
+    }
+
+    #[test]
+    fn database_rejects_duplicates_and_bad_patterns() {
+        let duplicate = SignatureDatabase::from_toml(r#"
+            [[signatures]]
+            name = "a"
+            pattern = "48"
+            module = "game.exe"
+
+            [[signatures]]
+            name = "a"
+            pattern = "49"
+            module = "game.exe"
+        "#);
+        assert!(matches!(duplicate, Err(DatabaseError::DuplicateName(name)) if name == "a"));
+
+        let invalid = SignatureDatabase::from_toml(r#"
+            [[signatures]]
+            name = "a"
+            pattern = "48 XX"
+            module = "game.exe"
+        "#);
+        assert!(matches!(invalid, Err(DatabaseError::Toml(_))));
+    }
+
+    #[test]
+    fn database_resolves_and_reports() {
+        let database = SignatureDatabase::from_toml(r#"
+            [[signatures]]
+            name = "global"
+            pattern = "48 8B 05 [?? ?? ?? ??] 48 85 C0"
+            module = "game.exe"
+            steps = [{ rip_relative = {} }, "deref", { offset = 8 }]
+
+            [[signatures]]
+            name = "instruction"
+            pattern = "48 8B 05 ?? ?? ?? ?? 48 85 C0"
+            module = "game.exe"
+            steps = [{ offset = 7 }]
+
+            [[signatures]]
+            name = "nop"
+            pattern = "0F 1F 40 00"
+            module = "game.exe"
+
+            [[signatures]]
+            name = "gone"
+            pattern = "DE AD BE EF"
+            module = "game.exe"
+
+            [[signatures]]
+            name = "other_module"
+            pattern = "DE AD BE EF"
+            module = "other.dll"
+
+            [[signatures]]
+            name = "bad_capture"
+            pattern = "48 8B 05 ?? ?? ?? ?? 48 85 C0"
+            module = "game.exe"
+            steps = [{ rip_relative = { capture = 1 } }]
+        "#).unwrap();
+
+        let resolution = database.resolve(&memory());
+        assert_eq!(resolution.get("global"), Some(GLOBAL + 8));
+        assert_eq!(resolution.get("instruction"), Some(TEXT_BASE + 0x17));
+        assert_eq!(resolution.get("nop"), None);
+        assert_eq!(resolution.addresses.len(), 2);
+
+        let report = &resolution.report;
+        let statuses = report.entries.iter()
+            .map(|e| (e.name.as_str(), &e.status, e.hits))
+            .collect::<Vec<_>>();
+
+        assert_eq!(statuses, vec![
+            ("global", &SignatureStatus::Found(GLOBAL + 8), Hits::Scanned(1)),
+            ("instruction", &SignatureStatus::Found(TEXT_BASE + 0x17), Hits::Scanned(1)),
+            ("nop", &SignatureStatus::Ambiguous(vec![TEXT_BASE + 0x40, TEXT_BASE + 0x50]), Hits::Scanned(2)),
+            ("gone", &SignatureStatus::Missing, Hits::Scanned(0)),
+            ("other_module", &SignatureStatus::Failed(ResolveError::SectionNotFound), Hits::NotScanned),
+            ("bad_capture", &SignatureStatus::Failed(ResolveError::UnknownCapture(CaptureRef::Index(1))), Hits::Scanned(1)),
+        ]);
+
+        assert!(!report.is_complete());
+        let rendered = report.to_string();
+        assert!(rendered.contains("nop: ambiguous, matches at 0x140001040, 0x140001050 (2 hits"), "{rendered}");
+        assert!(rendered.contains("other_module: failed, module or section not found (not scanned, "), "{rendered}");
+        assert!(rendered.contains("\n2 of 6 signatures found in "), "{rendered}");
+    }
+
+    #[test]
+    fn database_counts_overlapping_matches() {
+        let database = SignatureDatabase::from_toml(r#"
+            [[signatures]]
+            name = "nops"
+            pattern = "90 90"
+            module = "game.exe"
+        "#).unwrap();
+
+        let mut memory = BufferMemory::new();
+        memory.insert("game.exe", ".text", TEXT_BASE, vec![0xCC, 0x90, 0x90, 0x90, 0xCC]);
+
+        let resolution = database.resolve(&memory);
+        assert_eq!(resolution.get("nops"), None);
+        assert_eq!(resolution.report.entries[0].status, SignatureStatus::Ambiguous(vec![TEXT_BASE + 1, TEXT_BASE + 2]));
+    }
+
+    #[test]
+    fn database_uses_and_refreshes_the_cache() {
+        let database = SignatureDatabase::from_toml(r#"
+            [[signatures]]
+            name = "global"
+            pattern = "48 8B 05 [?? ?? ?? ??] 48 85 C0"
+            module = "game.exe"
+            steps = [{ rip_relative = {} }, "deref"]
+
+            [[signatures]]
+            name = "nop"
+            pattern = "0F 1F 40 00"
+            module = "game.exe"
+        "#).unwrap();
+
+        let mut memory = memory();
+        memory.insert_headers("game.exe", IMAGE_BASE, headers(1));
+
+        let mut cache = ScanCache::new();
+        let cached = |resolution: &crate::database::Resolution| resolution.report.entries.iter()
+            .map(|e| e.hits == Hits::Cached)
+            .collect::<Vec<_>>();
+
+        let first = database.resolve_cached(&memory, &mut cache);
+        assert_eq!(cached(&first), vec![false, false]);
+
+        // Only unique matches end up in the cache
+        let second = database.resolve_cached(&memory, &mut cache);
+        assert_eq!(cached(&second), vec![true, false]);
+        assert_eq!(second.get("global"), Some(GLOBAL));
+        assert!(second.report.to_string().contains(&format!("global: found at {GLOBAL:#x} (cached, ")));
+
+        // Same build but the bytes at the cached location changed
+        memory.insert("game.exe", ".text", TEXT_BASE, text(0x20));
+
+        let third = database.resolve_cached(&memory, &mut cache);
+        assert_eq!(cached(&third), vec![false, false]);
+        assert_eq!(third.get("global"), Some(GLOBAL));
+
+        // A new build drops everything cached for the module
+        memory.insert_headers("game.exe", IMAGE_BASE, headers(2));
+        let fourth = database.resolve_cached(&memory, &mut cache);
+        assert_eq!(cached(&fourth), vec![false, false]);
+        assert_eq!(cached(&database.resolve_cached(&memory, &mut cache)), vec![true, false]);
+    }
+}
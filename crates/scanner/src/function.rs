@@ -0,0 +1,167 @@
+use crate::pattern::Pattern;
+use crate::scanner::ScanOptions;
+use crate::scanner::simple::scan_reverse_with_options;
+use crate::pe::{data_directory, read_u32, EXCEPTION_DIRECTORY};
+
+/// MSVC aligns functions to this and fills the space in between with `int3`.
+const FUNCTION_ALIGNMENT: usize = 16;
+
+/// Set in the flags of an unwind info when it continues the unwind info of another entry.
+const UNW_FLAG_CHAININFO: u8 = 0x4;
+
+/// Chained unwind infos are followed at most this many times, in case they loop.
+const MAX_CHAIN_DEPTH: usize = 32;
+
+/// Finds the start of the function `address` is in. `base` is the address of the first byte of
+/// `region`.
+///
+/// If `region` is a mapped module starting with its PE headers the exception directory is looked
+/// at first, following chained entries to the function they belong to. Leaf functions don't have
+/// an entry, for those and for regions without headers the start is taken to be the first aligned
+/// byte after `int3` padding that comes before the address.
+pub fn find_function_start(region: &[u8], base: usize, address: usize) -> Option<usize> {
+    let offset = address.checked_sub(base)
+        .filter(|o| *o < region.len())?;
+
+    find_in_exception_directory(region, offset)
+        .or_else(|| find_after_padding(region, base, offset))
+        .map(|start| base + start)
+}
+
+/// Looks up the `RUNTIME_FUNCTION` that covers the RVA, the entries are sorted by their start.
+fn find_in_exception_directory(image: &[u8], rva: usize) -> Option<usize> {
+    let (directory, size) = data_directory(image, EXCEPTION_DIRECTORY)?;
+    let entries = image.get(directory..directory + size)?;
+
+    let entry = |index: usize| -> Option<(usize, usize, usize)> {
+        Some((
+            read_u32(entries, index * 12)? as usize,
+            read_u32(entries, index * 12 + 4)? as usize,
+            read_u32(entries, index * 12 + 8)? as usize,
+        ))
+    };
+
+    // Find the first entry that starts after the RVA, the one before it is the only candidate
+    let (mut low, mut high) = (0, size / 12);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        match entry(middle)?.0 <= rva {
+            true => low = middle + 1,
+            false => high = middle,
+        }
+    }
+
+    let (mut begin, end, mut unwind_info) = entry(low.checked_sub(1)?)?;
+    if rva >= end {
+        return None;
+    }
+
+    // Chained entries describe a part of a function that was split up, the entry of the function
+    // they belong to is stored after the unwind codes.
+    for _ in 0..MAX_CHAIN_DEPTH {
+        let flags = *image.get(unwind_info)? >> 3;
+        if flags & UNW_FLAG_CHAININFO == 0 {
+            return Some(begin);
+        }
+
+        // The amount of unwind codes is rounded up to an even amount
+        let codes = *image.get(unwind_info + 2)? as usize;
+        let parent = unwind_info + 4 + codes.div_ceil(2) * 4;
+
+        begin = read_u32(image, parent)? as usize;
+        unwind_info = read_u32(image, parent + 8)? as usize;
+    }
+
+    None
+}
+
+/// Walks backwards to the last `int3` that's followed by an aligned byte that isn't another `int3`.
+fn find_after_padding(region: &[u8], base: usize, offset: usize) -> Option<usize> {
+    let padding = Pattern::from_byte_slice(&[0xCC]);
+    // The padding byte has to sit right in front of an aligned address
+    let options = ScanOptions::aligned_at(base + 1, FUNCTION_ALIGNMENT);
+
+    let mut from = offset.checked_sub(1)?;
+    loop {
+        let location = scan_reverse_with_options(region, &padding, from, &options)?.location;
+        if region.get(location + 1).is_some_and(|b| *b != 0xCC) {
+            return Some(location + 1);
+        }
+
+        from = location.checked_sub(1)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::function::find_function_start;
+    use crate::pe::fixtures::{TestImage, IMAGE_BASE};
+    use crate::pe::EXCEPTION_DIRECTORY;
+
+    /// Start and end RVA of a function, along with the function a chained entry belongs to.
+    type Entry = (u32, u32, Option<(u32, u32)>);
+
+    /// Builds a PE32+ image with code at 0x1000 and the given entries in the exception directory at
+    /// 0x2000. Unwind infos go at 0x2800.
+    fn image(functions: &[Entry]) -> Vec<u8> {
+        let mut image = TestImage::new(0x3000)
+            .data_directory(EXCEPTION_DIRECTORY, 0x2000, functions.len() as u32 * 12)
+            .into_bytes();
+
+        image[0x1000..0x2000].fill(0xCC);
+        for (i, (begin, end, parent)) in functions.iter().enumerate() {
+            image[*begin as usize..*end as usize].fill(0x90);
+
+            let unwind_info = 0x2800 + i * 0x20;
+            let entry = 0x2000 + i * 12;
+            image[entry..entry + 4].copy_from_slice(&begin.to_le_bytes());
+            image[entry + 4..entry + 8].copy_from_slice(&end.to_le_bytes());
+            image[entry + 8..entry + 12].copy_from_slice(&(unwind_info as u32).to_le_bytes());
+
+            // Version 1 with a single unwind code, chained entries point at their parent
+            image[unwind_info] = 1;
+            image[unwind_info + 2] = 1;
+            if let Some((parent_begin, parent_end)) = parent {
+                image[unwind_info] |= 0x4 << 3;
+                image[unwind_info + 8..unwind_info + 12].copy_from_slice(&parent_begin.to_le_bytes());
+                image[unwind_info + 12..unwind_info + 16].copy_from_slice(&parent_end.to_le_bytes());
+                image[unwind_info + 16..unwind_info + 20].copy_from_slice(&0x2800u32.to_le_bytes());
+            }
+        }
+
+        image
+    }
+
+    #[test]
+    fn function_start_is_taken_from_the_exception_directory() {
+        let image = image(&[
+            (0x1000, 0x1040, None),
+            (0x1040, 0x1080, None),
+            (0x1100, 0x1120, Some((0x1000, 0x1040))),
+        ]);
+
+        assert_eq!(find_function_start(&image, IMAGE_BASE, IMAGE_BASE + 0x1020), Some(IMAGE_BASE + 0x1000));
+        // Functions without padding in between are told apart
+        assert_eq!(find_function_start(&image, IMAGE_BASE, IMAGE_BASE + 0x1040), Some(IMAGE_BASE + 0x1040));
+        assert_eq!(find_function_start(&image, IMAGE_BASE, IMAGE_BASE + 0x107F), Some(IMAGE_BASE + 0x1040));
+        // Chained entries resolve to the function they're part of
+        assert_eq!(find_function_start(&image, IMAGE_BASE, IMAGE_BASE + 0x1110), Some(IMAGE_BASE + 0x1000));
+    }
+
+    #[test]
+    fn function_start_falls_back_to_padding() {
+        let mut image = image(&[(0x1000, 0x1040, None)]);
+        // A leaf function without an entry, the int3 at 0x1207 isn't in front of an aligned byte
+        image[0x1200..0x1230].fill(0x90);
+        image[0x1207] = 0xCC;
+
+        assert_eq!(find_function_start(&image, IMAGE_BASE, IMAGE_BASE + 0x1220), Some(IMAGE_BASE + 0x1200));
+        assert_eq!(find_function_start(&image, IMAGE_BASE, IMAGE_BASE + 0x1200), Some(IMAGE_BASE + 0x1200));
+
+        // Plain code without headers, the region doesn't have to start aligned
+        let code = &image[0x11F8..0x1240];
+        assert_eq!(find_function_start(code, IMAGE_BASE + 0x11F8, IMAGE_BASE + 0x1220), Some(IMAGE_BASE + 0x1200));
+        assert_eq!(find_function_start(code, IMAGE_BASE + 0x11F8, IMAGE_BASE + 0x11F9), None);
+        assert_eq!(find_function_start(code, IMAGE_BASE + 0x11F8, IMAGE_BASE + 0x1240), None);
+    }
+}
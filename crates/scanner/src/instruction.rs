@@ -0,0 +1,570 @@
+use std::fmt;
+use std::error::Error;
+
+use iced_x86::{ConstantOffsets, Decoder, DecoderOptions, Instruction, Mnemonic, OpKind, Register};
+
+use crate::pattern::tokenizer::{is_capture_group_name, Span};
+use crate::scanner::{MatchMode, ScanResult};
+use crate::scanner::result::ScanResultCapture;
+
+/// A sequence of instructions to match against decoded 64-bit code, instead of against the bytes
+/// that encode them. This keeps matching when the compiler picks another register or encoding.
+///
+/// Instructions are separated by `;` and consist of a mnemonic followed by comma separated
+/// operands:
+///
+/// - `?` as the mnemonic matches any instruction, mnemonics can be combined with `|` like `je|jne`.
+/// - Leaving out the operands matches any operands.
+/// - `?` matches any operand.
+/// - `rax`, `al`, `xmm0` and such match that exact register, note that `r8` is the register.
+/// - `reg` matches any register, `r16`, `r32` and `r64` match any general purpose register of
+///   that size.
+/// - `imm` matches any immediate.
+/// - A number like `0x10` or `-8` matches an immediate or branch target with that value.
+/// - `[base+displacement]` matches a memory operand without an index register. The base is a
+///   register, `rip` or `?` for any base, in which case the index isn't checked either. The
+///   displacement is a number or `?`, leaving it out requires it to be zero. For `rip` it's the
+///   displacement as encoded, relative to the end of the instruction. `[?]` matches any memory
+///   operand.
+///
+/// Operands are captured by wrapping them in `{}`, optionally named like in byte patterns:
+/// `mov r64, {global: [rip+?]}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstructionPattern {
+    instructions: Vec<InstructionMatcher>,
+    /// Names of the captures, by the index of the capture. `None` for unnamed captures.
+    capture_names: Vec<Option<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct InstructionMatcher {
+    /// `None` matches any mnemonic.
+    mnemonics: Option<Vec<Mnemonic>>,
+    /// `None` matches any amount of operands.
+    operands: Option<Vec<OperandMatcher>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct OperandMatcher {
+    kind: OperandKind,
+    /// Index of the capture the operand's value goes in.
+    capture: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum OperandKind {
+    Any,
+    Register(Register),
+    AnyRegister,
+    /// Any general purpose register of the size in bytes.
+    GeneralPurposeRegister(usize),
+    Immediate,
+    /// An immediate or a branch target with this value.
+    Value(u64),
+    Memory {
+        /// `None` matches any base and index.
+        base: Option<Register>,
+        /// `None` matches any displacement.
+        displacement: Option<u64>,
+    },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum InstructionPatternError {
+    /// There's nothing in between two `;`, the span points at the second one.
+    EmptyInstruction(Span),
+    UnknownMnemonic(Span),
+    InvalidOperand(Span),
+    /// Two captures in the pattern share the same name.
+    DuplicateCaptureName(String, Span),
+}
+
+impl InstructionPatternError {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::EmptyInstruction(span)
+            | Self::UnknownMnemonic(span)
+            | Self::InvalidOperand(span)
+            | Self::DuplicateCaptureName(_, span) => *span,
+        }
+    }
+}
+
+impl fmt::Display for InstructionPatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyInstruction(span) => write!(f, "empty instruction at {span}"),
+            Self::UnknownMnemonic(span) => write!(f, "unknown mnemonic at {span}"),
+            Self::InvalidOperand(span) => write!(f, "invalid operand at {span}"),
+            Self::DuplicateCaptureName(name, span) => write!(
+                f,
+                "capture name `{name}` at {span} is already in use",
+            ),
+        }
+    }
+}
+
+impl Error for InstructionPatternError {}
+
+impl InstructionPattern {
+    pub fn parse(input: &str) -> Result<Self, InstructionPatternError> {
+        let mut instructions = Vec::new();
+        let mut capture_names = Vec::new();
+
+        let mut start = 0;
+        for part in input.split(';') {
+            let offset = start + (part.len() - part.trim_start().len());
+            start += part.len() + 1;
+
+            // A trailing `;` is allowed
+            if part.trim().is_empty() {
+                match start > input.len() && !instructions.is_empty() {
+                    true => continue,
+                    false => return Err(InstructionPatternError::EmptyInstruction(Span::at(input, start - 1))),
+                }
+            }
+
+            instructions.push(parse_instruction(input, offset, part.trim(), &mut capture_names)?);
+        }
+
+        Ok(Self { instructions, capture_names })
+    }
+
+    pub fn capture_names(&self) -> &[Option<String>] {
+        &self.capture_names
+    }
+}
+
+fn parse_instruction(
+    input: &str,
+    offset: usize,
+    instruction: &str,
+    capture_names: &mut Vec<Option<String>>,
+) -> Result<InstructionMatcher, InstructionPatternError> {
+    let (mnemonic, operands) = match instruction.split_once(char::is_whitespace) {
+        Some((mnemonic, operands)) => (mnemonic, Some(operands)),
+        None => (instruction, None),
+    };
+
+    let mnemonics = match mnemonic {
+        "?" => None,
+        _ => Some(
+            mnemonic.split('|')
+                .map(|name| {
+                    Mnemonic::values()
+                        .find(|m| *m != Mnemonic::INVALID && format!("{m:?}").eq_ignore_ascii_case(name))
+                        .ok_or_else(|| InstructionPatternError::UnknownMnemonic(Span::at(input, offset)))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+    };
+
+    let operands = match operands {
+        None => None,
+        Some(operands) => {
+            let mut operand_offset = offset + mnemonic.len() + 1;
+            let mut matchers = Vec::new();
+
+            for operand in operands.split(',') {
+                let trimmed_offset = operand_offset + (operand.len() - operand.trim_start().len());
+                operand_offset += operand.len() + 1;
+
+                matchers.push(parse_operand(input, trimmed_offset, operand.trim(), capture_names)?);
+            }
+
+            Some(matchers)
+        },
+    };
+
+    Ok(InstructionMatcher { mnemonics, operands })
+}
+
+fn parse_operand(
+    input: &str,
+    offset: usize,
+    operand: &str,
+    capture_names: &mut Vec<Option<String>>,
+) -> Result<OperandMatcher, InstructionPatternError> {
+    let invalid = || InstructionPatternError::InvalidOperand(Span::at(input, offset));
+
+    let Some(captured) = operand.strip_prefix('{') else {
+        return Ok(OperandMatcher { kind: parse_operand_kind(operand).ok_or_else(invalid)?, capture: None });
+    };
+
+    let captured = captured.strip_suffix('}').ok_or_else(invalid)?;
+    let (name, operand) = match captured.split_once(':') {
+        Some((name, operand)) if is_capture_group_name(name.trim()) => (Some(name.trim()), operand.trim()),
+        _ => (None, captured.trim()),
+    };
+
+    if let Some(name) = name {
+        if capture_names.iter().any(|n| n.as_deref() == Some(name)) {
+            return Err(InstructionPatternError::DuplicateCaptureName(name.to_string(), Span::at(input, offset)));
+        }
+    }
+
+    let kind = parse_operand_kind(operand).ok_or_else(invalid)?;
+    capture_names.push(name.map(str::to_string));
+
+    Ok(OperandMatcher { kind, capture: Some(capture_names.len() - 1) })
+}
+
+fn parse_operand_kind(operand: &str) -> Option<OperandKind> {
+    let kind = match operand.to_ascii_lowercase().as_str() {
+        "?" => OperandKind::Any,
+        "reg" => OperandKind::AnyRegister,
+        "r16" => OperandKind::GeneralPurposeRegister(2),
+        "r32" => OperandKind::GeneralPurposeRegister(4),
+        "r64" => OperandKind::GeneralPurposeRegister(8),
+        "imm" => OperandKind::Immediate,
+        operand if operand.starts_with('[') => {
+            let memory = operand.strip_prefix('[')?.strip_suffix(']')?;
+            let memory = memory.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+
+            let (base, displacement) = match memory.find(['+', '-']) {
+                Some(sign) => (&memory[..sign], Some(&memory[sign..])),
+                None => (memory.as_str(), None),
+            };
+
+            let base = match base {
+                "?" => None,
+                _ => Some(parse_register(base)?),
+            };
+
+            let displacement = match displacement {
+                None if base.is_none() => None,
+                None => Some(0),
+                Some(d) if d[1..] == *"?" => None,
+                Some(d) => Some(parse_number(d.strip_prefix('+').unwrap_or(d))?),
+            };
+
+            OperandKind::Memory { base, displacement }
+        },
+        operand => match parse_number(operand) {
+            Some(value) => OperandKind::Value(value),
+            None => OperandKind::Register(parse_register(operand)?),
+        },
+    };
+
+    Some(kind)
+}
+
+fn parse_register(name: &str) -> Option<Register> {
+    Register::values()
+        .find(|r| *r != Register::None && format!("{r:?}").eq_ignore_ascii_case(name))
+}
+
+/// Parses a decimal or `0x` prefixed hex number, negative numbers are sign-extended.
+fn parse_number(number: &str) -> Option<u64> {
+    let (negative, number) = match number.strip_prefix('-') {
+        Some(number) => (true, number),
+        None => (false, number),
+    };
+
+    let value = match number.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok()?,
+        None => number.parse::<u64>().ok()?,
+    };
+
+    Some(if negative { value.wrapping_neg() } else { value })
+}
+
+impl InstructionMatcher {
+    fn matches(&self, instruction: &Instruction) -> bool {
+        if let Some(mnemonics) = &self.mnemonics {
+            if !mnemonics.contains(&instruction.mnemonic()) {
+                return false;
+            }
+        }
+
+        match &self.operands {
+            None => true,
+            Some(operands) => operands.len() == instruction.op_count() as usize
+                && operands.iter()
+                    .enumerate()
+                    .all(|(i, o)| o.kind.matches(instruction, i as u32)),
+        }
+    }
+}
+
+impl OperandKind {
+    fn matches(&self, instruction: &Instruction, operand: u32) -> bool {
+        let kind = instruction.op_kind(operand);
+
+        match self {
+            Self::Any => true,
+            Self::Register(register) => kind == OpKind::Register && instruction.op_register(operand) == *register,
+            Self::AnyRegister => kind == OpKind::Register,
+            Self::GeneralPurposeRegister(size) => {
+                kind == OpKind::Register && general_purpose_size(instruction.op_register(operand)) == Some(*size)
+            },
+            Self::Immediate => is_immediate(kind),
+            Self::Value(value) => match kind {
+                _ if is_immediate(kind) => instruction.immediate(operand) == *value,
+                OpKind::NearBranch16 | OpKind::NearBranch32 | OpKind::NearBranch64 => {
+                    instruction.near_branch_target() == *value
+                },
+                _ => false,
+            },
+            Self::Memory { base, displacement } => {
+                if kind != OpKind::Memory {
+                    return false;
+                }
+
+                let base_matches = match base {
+                    None => true,
+                    Some(base) => instruction.memory_base() == *base && instruction.memory_index() == Register::None,
+                };
+
+                // iced resolves RIP-relative displacements to the address they refer to
+                let encoded = match instruction.is_ip_rel_memory_operand() {
+                    true => instruction.memory_displacement64().wrapping_sub(instruction.next_ip()),
+                    false => instruction.memory_displacement64(),
+                };
+
+                base_matches && displacement.is_none_or(|d| encoded == d)
+            },
+        }
+    }
+}
+
+/// Size in bytes of a 16, 32 or 64-bit general purpose register. Goes by the order of iced's
+/// `Register` as `Register::size` requires the `instr_info` feature.
+fn general_purpose_size(register: Register) -> Option<usize> {
+    let register = register as u32;
+    [(Register::AX, Register::R15W, 2), (Register::EAX, Register::R15D, 4), (Register::RAX, Register::R15, 8)]
+        .into_iter()
+        .find(|(first, last, _)| (*first as u32..=*last as u32).contains(&register))
+        .map(|(_, _, size)| size)
+}
+
+fn is_immediate(kind: OpKind) -> bool {
+    matches!(
+        kind,
+        OpKind::Immediate8
+            | OpKind::Immediate8_2nd
+            | OpKind::Immediate16
+            | OpKind::Immediate32
+            | OpKind::Immediate64
+            | OpKind::Immediate8to16
+            | OpKind::Immediate8to32
+            | OpKind::Immediate8to64
+            | OpKind::Immediate32to64
+    )
+}
+
+/// The value of an operand along with where it's encoded, relative to the start of the
+/// instruction. Memory operands give their displacement, or the address they refer to for
+/// RIP-relative operands. Registers give their iced `Register` value.
+fn operand_value(instruction: &Instruction, offsets: &ConstantOffsets, operand: u32) -> (u64, usize) {
+    match instruction.op_kind(operand) {
+        OpKind::Register => (instruction.op_register(operand) as u64, 0),
+        OpKind::Memory if instruction.is_ip_rel_memory_operand() => {
+            (instruction.ip_rel_memory_address(), offsets.displacement_offset())
+        },
+        OpKind::Memory => (instruction.memory_displacement64(), offsets.displacement_offset()),
+        OpKind::NearBranch16 | OpKind::NearBranch32 | OpKind::NearBranch64 => {
+            (instruction.near_branch_target(), offsets.immediate_offset())
+        },
+        OpKind::Immediate8_2nd => (instruction.immediate(operand), offsets.immediate_offset2()),
+        kind if is_immediate(kind) => (instruction.immediate(operand), offsets.immediate_offset()),
+        _ => (0, 0),
+    }
+}
+
+/// Finds the first match of the pattern in `code`. `base` is the address of the first byte of
+/// `code`, which RIP-relative operands and branch targets are resolved against.
+pub fn scan(code: &[u8], base: usize, pattern: &InstructionPattern) -> Option<ScanResult> {
+    let mut decoder = Decoder::with_ip(64, code, base as u64, DecoderOptions::NONE);
+
+    (0..code.len())
+        .find_map(|offset| match_at(&mut decoder, base, offset, pattern))
+        .map(|(result, _)| result)
+}
+
+/// Finds every match of the pattern in `code`, every offset is tried as the start of the first
+/// instruction. Locations are relative to the start of `code`. Captures hold the value of the
+/// operand as 8 little-endian bytes and point at where it's encoded in `code`.
+pub fn scan_all(code: &[u8], base: usize, pattern: &InstructionPattern, mode: MatchMode) -> Vec<ScanResult> {
+    let mut decoder = Decoder::with_ip(64, code, base as u64, DecoderOptions::NONE);
+    let mut results = Vec::new();
+
+    let mut offset = 0;
+    while offset < code.len() {
+        match match_at(&mut decoder, base, offset, pattern) {
+            Some((result, end)) => {
+                results.push(result);
+
+                offset = match mode {
+                    MatchMode::NonOverlapping => end,
+                    MatchMode::Overlapping => offset + 1,
+                };
+            },
+            None => offset += 1,
+        }
+    }
+
+    results
+}
+
+/// Matches the pattern against the instructions starting at `offset`. Returns the result and the
+/// offset right after the last matched instruction.
+fn match_at(
+    decoder: &mut Decoder,
+    base: usize,
+    offset: usize,
+    pattern: &InstructionPattern,
+) -> Option<(ScanResult, usize)> {
+    decoder.set_position(offset).ok()?;
+    decoder.set_ip((base + offset) as u64);
+
+    let mut captures = pattern.capture_names.iter()
+        .map(|name| ScanResultCapture { location: 0, bytes: vec![], name: name.clone() })
+        .collect::<Vec<_>>();
+
+    let mut instruction = Instruction::default();
+    for matcher in pattern.instructions.iter() {
+        if !decoder.can_decode() {
+            return None;
+        }
+
+        let start = decoder.position();
+        decoder.decode_out(&mut instruction);
+        if instruction.is_invalid() || !matcher.matches(&instruction) {
+            return None;
+        }
+
+        let offsets = decoder.get_constant_offsets(&instruction);
+        for (i, operand) in matcher.operands.iter().flatten().enumerate() {
+            if let Some(capture) = operand.capture {
+                let (value, location) = operand_value(&instruction, &offsets, i as u32);
+                captures[capture].location = start + location;
+                captures[capture].bytes = value.to_le_bytes().to_vec();
+            }
+        }
+    }
+
+    Some((ScanResult { location: offset, captures }, decoder.position()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::instruction::{scan, scan_all, InstructionPattern, InstructionPatternError};
+    use crate::pe::fixtures::{Code, TEXT_BASE};
+    use crate::pattern::tokenizer::Span;
+    use crate::scanner::MatchMode;
+
+    /// Two variants of the same code, compiled with different registers and encodings.
+    fn code() -> Vec<u8> {
+        Code::new(TEXT_BASE)
+            // mov rcx, [rip+0x100]; call $+0x50; test al, al; jz short $+4
+            .at(0x10)
+            .rip_relative(&[0x48, 0x8B, 0x0D], TEXT_BASE + 0x17 + 0x100, &[])
+            .call(TEXT_BASE + 0x17 + 0x50)
+            .bytes(&[0x84, 0xC0])
+            .bytes(&[0x74, 0x02])
+            // mov r8, [rip+0x200]; call $+0x60; test al, al; jz near $+8
+            .at(0x30)
+            .rip_relative(&[0x4C, 0x8B, 0x05], TEXT_BASE + 0x37 + 0x200, &[])
+            .call(TEXT_BASE + 0x37 + 0x60)
+            .bytes(&[0x84, 0xC0])
+            .bytes(&[0x0F, 0x84, 0x02, 0x00, 0x00, 0x00])
+            .into_bytes()
+    }
+
+    #[test]
+    fn instruction_pattern_matches_across_registers_and_encodings() {
+        let code = code();
+        let pattern = InstructionPattern::parse("mov r64, {global: [rip+?]}; call {?}; test al, al; je ?").unwrap();
+
+        let results = scan_all(&code, TEXT_BASE, &pattern, MatchMode::NonOverlapping);
+        assert_eq!(results.iter().map(|r| r.location).collect::<Vec<_>>(), vec![0x10, 0x30]);
+
+        // RIP-relative operands and branch targets are resolved
+        let first = &results[0];
+        assert_eq!(first.named("global").unwrap().as_u64(), Ok((TEXT_BASE + 0x17 + 0x100) as u64));
+        assert_eq!(first.named("global").unwrap().location, 0x13);
+        assert_eq!(first.capture(1).unwrap().as_u64(), Ok((TEXT_BASE + 0x17 + 0x50) as u64));
+        assert_eq!(first.capture(1).unwrap().location, 0x18);
+
+        let second = &results[1];
+        assert_eq!(second.named("global").unwrap().as_u64(), Ok((TEXT_BASE + 0x37 + 0x200) as u64));
+    }
+
+    #[test]
+    fn instruction_pattern_wildcards() {
+        let code = code();
+
+        let exact = InstructionPattern::parse("mov rcx, [rip+?]").unwrap();
+        assert_eq!(scan(&code, TEXT_BASE, &exact).map(|r| r.location), Some(0x10));
+
+        let register = InstructionPattern::parse("mov r8, [rip+?]").unwrap();
+        assert_eq!(scan(&code, TEXT_BASE, &register).map(|r| r.location), Some(0x30));
+
+        let any_mnemonic = InstructionPattern::parse("? al, al; jne|je").unwrap();
+        assert_eq!(scan_all(&code, TEXT_BASE, &any_mnemonic, MatchMode::NonOverlapping).len(), 2);
+
+        let any_instruction = InstructionPattern::parse("call imm; ?; je 0x140001022").unwrap();
+        assert_eq!(scan_all(&code, TEXT_BASE, &any_instruction, MatchMode::NonOverlapping).len(), 0);
+
+        let branch_target = InstructionPattern::parse("test al, al; je 0x140001022").unwrap();
+        assert_eq!(scan(&code, TEXT_BASE, &branch_target).map(|r| r.location), Some(0x1C));
+
+        let displacement = InstructionPattern::parse("mov r64, [rip+0x200]").unwrap();
+        assert_eq!(scan(&code, TEXT_BASE, &displacement).map(|r| r.location), Some(0x30));
+
+        // The displacement is the encoded one, not the address it resolves to
+        let wrong_displacement = InstructionPattern::parse("mov r64, [rip+0x140001237]").unwrap();
+        assert_eq!(scan(&code, TEXT_BASE, &wrong_displacement), None);
+
+        let wrong_size = InstructionPattern::parse("mov r16, [rip+?]").unwrap();
+        assert_eq!(scan(&code, TEXT_BASE, &wrong_size), None);
+    }
+
+    #[test]
+    fn instruction_pattern_memory_operands() {
+        // mov rax, [rcx+0x10]; mov rax, [rcx+rdx*8+0x10]; mov rax, [rcx]
+        let code = [
+            0x48, 0x8B, 0x41, 0x10,
+            0x48, 0x8B, 0x44, 0xD1, 0x10,
+            0x48, 0x8B, 0x01,
+        ];
+
+        let locations = |pattern: &str| {
+            let pattern = InstructionPattern::parse(pattern).unwrap();
+            scan_all(&code, TEXT_BASE, &pattern, MatchMode::NonOverlapping).iter()
+                .map(|r| r.location)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(locations("mov rax, [rcx+0x10]"), vec![0]);
+        assert_eq!(locations("mov rax, [rcx+?]"), vec![0, 9]);
+        assert_eq!(locations("mov rax, [rcx]"), vec![9]);
+        assert_eq!(locations("mov rax, [?+16]"), vec![0, 4]);
+        assert_eq!(locations("mov reg, [?]"), vec![0, 4, 9]);
+    }
+
+    #[test]
+    fn instruction_pattern_reports_errors() {
+        assert_eq!(
+            InstructionPattern::parse("mov rax, rcx; frobnicate"),
+            Err(InstructionPatternError::UnknownMnemonic(Span { offset: 14, character: Some('f') })),
+        );
+        assert_eq!(
+            InstructionPattern::parse("mov rax, [rip+?"),
+            Err(InstructionPatternError::InvalidOperand(Span { offset: 9, character: Some('[') })),
+        );
+        assert_eq!(
+            InstructionPattern::parse("mov rax, rcx;; ret"),
+            Err(InstructionPatternError::EmptyInstruction(Span { offset: 13, character: Some(';') })),
+        );
+        assert_eq!(
+            InstructionPattern::parse("mov {a: rax}, {a: rcx}"),
+            Err(InstructionPatternError::DuplicateCaptureName(
+                "a".to_string(),
+                Span { offset: 14, character: Some('{') },
+            )),
+        );
+        assert!(InstructionPattern::parse("ret;").is_ok());
+    }
+}
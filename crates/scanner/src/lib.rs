@@ -1,2 +1,17 @@
 pub mod pattern;
-pub mod scanner;
\ No newline at end of file
+pub mod scanner;
+pub mod function;
+pub mod strings;
+mod pe;
+#[cfg(feature = "sigmaker")]
+pub mod sigmaker;
+#[cfg(feature = "instructions")]
+pub mod instruction;
+#[cfg(feature = "xref")]
+pub mod xref;
+#[cfg(feature = "module")]
+pub mod module;
+#[cfg(feature = "database")]
+pub mod database;
+#[cfg(feature = "cache")]
+pub mod cache;
@@ -0,0 +1,186 @@
+use crate::pattern::Pattern;
+use crate::pe::{IMAGE_SCN_MEM_EXECUTE, IMAGE_SCN_MEM_READ};
+use crate::scanner::MatchMode;
+use crate::scanner::result::ScanResultCapture;
+use crate::scanner::simple::scan_all;
+
+/// Which sections of a module are scanned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SectionFilter {
+    /// The section with this name, like `.text`.
+    Name(String),
+    /// Every section that holds executable code.
+    Executable,
+    /// Every section that can be read from.
+    Readable,
+    /// Every section.
+    All,
+}
+
+impl SectionFilter {
+    pub fn named(name: impl Into<String>) -> Self {
+        Self::Name(name.into())
+    }
+
+    /// Checks a section by its name and the `IMAGE_SCN_*` flags in its header.
+    pub fn accepts(&self, name: &str, characteristics: u32) -> bool {
+        match self {
+            Self::Name(n) => n == name,
+            Self::Executable => characteristics & IMAGE_SCN_MEM_EXECUTE != 0,
+            Self::Readable => characteristics & IMAGE_SCN_MEM_READ != 0,
+            Self::All => true,
+        }
+    }
+
+    /// Whether a section is scanned. Sections that can't be read from are skipped, even if the
+    /// filter accepts them.
+    fn scans(&self, name: &str, characteristics: u32) -> bool {
+        characteristics & IMAGE_SCN_MEM_READ != 0 && self.accepts(name, characteristics)
+    }
+}
+
+/// A section of a module along with the address its first byte is mapped at.
+#[derive(Debug, Clone, Copy)]
+pub struct ModuleSection<'a> {
+    pub name: &'a str,
+    pub address: usize,
+    pub bytes: &'a [u8],
+    /// The `IMAGE_SCN_*` flags from the section header.
+    pub characteristics: u32,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ModuleScanResult {
+    pub module: String,
+    pub section: String,
+    /// Address of the start of the match.
+    pub address: usize,
+    /// Address of the start of the match, relative to the module base.
+    pub rva: usize,
+    /// The captures of the match, located at their address.
+    pub captures: Vec<ScanResultCapture>,
+}
+
+impl ModuleScanResult {
+    /// Retrieves a capture by the index of its capture group in the pattern.
+    pub fn capture(&self, index: usize) -> Option<&ScanResultCapture> {
+        self.captures.get(index)
+    }
+
+    /// Retrieves a capture by the name of its capture group in the pattern.
+    pub fn named(&self, name: &str) -> Option<&ScanResultCapture> {
+        self.captures.iter()
+            .find(|c| c.name.as_deref() == Some(name))
+    }
+}
+
+/// Scans every readable section the filter accepts for non-overlapping matches. `base` is the
+/// address the module is loaded at, the results are ordered by section and then by address.
+pub fn scan_sections<'a>(
+    module: &str,
+    base: usize,
+    sections: impl IntoIterator<Item = ModuleSection<'a>>,
+    pattern: &Pattern,
+    filter: &SectionFilter,
+) -> Vec<ModuleScanResult> {
+    let sections = sections.into_iter()
+        .filter(|s| filter.scans(s.name, s.characteristics));
+
+    scan_each(module, base, sections, pattern)
+}
+
+fn scan_each<'a>(
+    module: &str,
+    base: usize,
+    sections: impl IntoIterator<Item = ModuleSection<'a>>,
+    pattern: &Pattern,
+) -> Vec<ModuleScanResult> {
+    let mut results = Vec::new();
+
+    for section in sections {
+        results.extend(
+            scan_all(section.bytes, pattern, MatchMode::NonOverlapping)
+                .into_iter()
+                .map(|r| ModuleScanResult {
+                    module: module.to_string(),
+                    section: section.name.to_string(),
+                    address: section.address + r.location,
+                    rva: section.address + r.location - base,
+                    captures: r.captures.into_iter()
+                        .map(|c| ScanResultCapture { location: section.address + c.location, ..c })
+                        .collect(),
+                })
+        );
+    }
+
+    results
+}
+
+/// Scans the readable sections of a module loaded in the current process that the filter accepts.
+#[cfg(windows)]
+pub fn scan_module(
+    module: &str,
+    pattern: &Pattern,
+    filter: &SectionFilter,
+) -> Result<Vec<ModuleScanResult>, broadsword_microsoft_runtime::module::LookupError> {
+    use broadsword_microsoft_runtime::module::{get_module_handle, get_module_sections_by_base};
+
+    let base = get_module_handle(module)?;
+    // Filtered before the sections are turned into slices, the bytes of the others can't be read
+    let sections = get_module_sections_by_base(base)
+        .filter(|s| filter.scans(&s.name, s.characteristics))
+        .collect::<Vec<_>>();
+
+    let sections = sections.iter().map(|s| ModuleSection {
+        name: &s.name,
+        address: s.memory_range.start,
+        // SAFETY: the section is mapped for as long as the module is loaded
+        bytes: unsafe { std::slice::from_raw_parts(s.memory_range.start as *const u8, s.memory_range.len()) },
+        characteristics: s.characteristics,
+    });
+
+    Ok(scan_each(module, base, sections, pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::module::{scan_sections, ModuleSection, SectionFilter};
+    use crate::pattern::Pattern;
+    use crate::pe::fixtures::{IMAGE_BASE, TEXT_BASE};
+
+    #[test]
+    fn sections_are_scanned_by_filter() {
+        let mut text = vec![0xCC; 0x40];
+        text[0x10..0x15].copy_from_slice(&[0xE8, 0x78, 0x56, 0x34, 0x12]);
+        let mut rdata = vec![0x00; 0x20];
+        rdata[0x08..0x0D].copy_from_slice(&[0xE8, 0xEF, 0xBE, 0xAD, 0xDE]);
+        // Discardable and not readable, it's never scanned
+        let discarded = rdata.clone();
+
+        let sections = [
+            ModuleSection { name: ".text", address: TEXT_BASE, bytes: &text, characteristics: 0x60000020 },
+            ModuleSection { name: ".rdata", address: IMAGE_BASE + 0x3000, bytes: &rdata, characteristics: 0x40000040 },
+            ModuleSection { name: ".gone", address: IMAGE_BASE + 0x4000, bytes: &discarded, characteristics: 0x02000040 },
+        ];
+
+        let pattern = Pattern::from_byte_pattern("E8 [target: ?? ?? ?? ??]").unwrap();
+        let scan = |filter| scan_sections("game.exe", IMAGE_BASE, sections, &pattern, &filter);
+
+        let results = scan(SectionFilter::Readable);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].module, "game.exe");
+        assert_eq!(results[0].section, ".text");
+        assert_eq!(results[0].address, IMAGE_BASE + 0x1010);
+        assert_eq!(results[0].rva, 0x1010);
+        assert_eq!(results[0].named("target").unwrap().location, IMAGE_BASE + 0x1011);
+        assert_eq!(results[0].capture(0).unwrap().as_u32(), Ok(0x12345678));
+        assert_eq!(results[1].section, ".rdata");
+        assert_eq!(results[1].rva, 0x3008);
+
+        assert_eq!(scan(SectionFilter::Executable).len(), 1);
+        assert_eq!(scan(SectionFilter::named(".rdata"))[0].rva, 0x3008);
+        assert_eq!(scan(SectionFilter::named(".data")), vec![]);
+        assert_eq!(scan(SectionFilter::All).len(), 2);
+        assert_eq!(scan(SectionFilter::named(".gone")), vec![]);
+    }
+}
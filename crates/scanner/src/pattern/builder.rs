@@ -0,0 +1,347 @@
+use std::fmt;
+use std::error::Error;
+use std::ops::Range;
+
+use crate::pattern::{Alternatives, Jump, Pattern};
+use crate::pattern::tokenizer::is_capture_group_name;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuilderError {
+    /// A capture group was opened while another one was still open, holds the offset of the byte
+    /// it was opened at.
+    CaptureGroupAlreadyOpened(usize),
+    /// A capture group was closed at the offset without being opened.
+    CaptureGroupNotOpened(usize),
+    /// The capture group opened at the offset was still open when building the pattern.
+    CaptureGroupNotClosed(usize),
+    /// Two capture groups in the pattern share the same name.
+    DuplicateCaptureGroupName(String),
+    /// A capture group name that the byte pattern syntax wouldn't accept.
+    InvalidCaptureGroupName(String),
+    /// A jump was placed at the offset inside of a capture group.
+    JumpInCaptureGroup(usize),
+    /// A jump was placed at the start or end of the pattern where it has nothing to skip between.
+    JumpAtPatternEdge(usize),
+    /// A jump at the offset has a larger minimum than maximum.
+    InvalidJumpRange(usize),
+}
+
+impl fmt::Display for BuilderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CaptureGroupAlreadyOpened(offset) => write!(
+                f,
+                "capture group opened at byte {offset} while another capture group is still open",
+            ),
+            Self::CaptureGroupNotOpened(offset) => write!(f, "capture group closed at byte {offset} was never opened"),
+            Self::CaptureGroupNotClosed(offset) => write!(f, "capture group opened at byte {offset} is never closed"),
+            Self::DuplicateCaptureGroupName(name) => write!(f, "capture group name `{name}` is already in use"),
+            Self::InvalidCaptureGroupName(name) => write!(f, "`{name}` is not a valid capture group name"),
+            Self::JumpInCaptureGroup(offset) => write!(f, "jump at byte {offset} is inside of a capture group"),
+            Self::JumpAtPatternEdge(offset) => write!(f, "jump at byte {offset} is at the start or end of the pattern"),
+            Self::InvalidJumpRange(offset) => write!(f, "jump at byte {offset} has a minimum larger than its maximum"),
+        }
+    }
+}
+
+impl Error for BuilderError {}
+
+/// Builds a `Pattern` in code, without going through a pattern string. Offsets in errors are the
+/// amount of bytes in the pattern at the point the error occurred. The first error is kept and
+/// returned by `build`, everything after it is ignored.
+#[derive(Debug, Clone)]
+pub struct PatternBuilder {
+    pattern: Pattern,
+    /// Where the open capture group starts along with its name.
+    open_capture: Option<(usize, Option<String>)>,
+    error: Option<BuilderError>,
+}
+
+impl Default for PatternBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PatternBuilder {
+    pub fn new() -> Self {
+        Self {
+            pattern: Pattern::from_byte_vec(vec![]),
+            open_capture: None,
+            error: None,
+        }
+    }
+
+    /// Appends bytes that have to match exactly.
+    pub fn bytes(mut self, bytes: &[u8]) -> Self {
+        for byte in bytes.iter() {
+            self.push(*byte, 0xFF);
+        }
+
+        self
+    }
+
+    /// Appends `count` bytes that match anything, like `??`.
+    pub fn wildcard(mut self, count: usize) -> Self {
+        for _ in 0..count {
+            self.push(0x00, 0x00);
+        }
+
+        self
+    }
+
+    /// Appends a byte where only the bits set in `mask` have to match.
+    pub fn masked(mut self, byte: u8, mask: u8) -> Self {
+        self.push(byte, mask);
+        self
+    }
+
+    /// Appends the little-endian bytes of `value`.
+    pub fn u32_le(self, value: u32) -> Self {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    /// Appends the little-endian bytes of `value`, for `rel32` and `disp32` operands.
+    pub fn i32_le(self, value: i32) -> Self {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    /// Appends the little-endian bytes of `value`.
+    pub fn u64_le(self, value: u64) -> Self {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    /// Skips anywhere from `min` up to and including `max` bytes, like `{min-max}`.
+    pub fn jump(mut self, min: usize, max: usize) -> Self {
+        self.push_jump(Jump { position: self.pattern.length, min, max });
+        self
+    }
+
+    /// Opens an unnamed capture group, like `[`.
+    pub fn begin_capture(mut self) -> Self {
+        self.open(None);
+        self
+    }
+
+    /// Opens a named capture group, like `[name:`.
+    pub fn begin_named_capture(mut self, name: &str) -> Self {
+        if !is_capture_group_name(name) {
+            self.fail(BuilderError::InvalidCaptureGroupName(name.to_string()));
+        }
+
+        self.open(Some(name.to_string()));
+        self
+    }
+
+    /// Closes the open capture group, like `]`.
+    pub fn end_capture(mut self) -> Self {
+        match self.open_capture.take() {
+            Some((start, name)) => {
+                let end = self.pattern.length;
+                self.pattern.capture_groups.push(Range { start, end });
+                self.pattern.capture_names.push(name);
+            },
+            None => self.fail(BuilderError::CaptureGroupNotOpened(self.pattern.length)),
+        }
+
+        self
+    }
+
+    /// Appends another pattern along with its capture groups, jumps and alternatives.
+    pub fn concat(mut self, other: &Pattern) -> Self {
+        let offset = self.pattern.length;
+
+        if !other.capture_groups.is_empty() && self.open_capture.is_some() {
+            self.fail(BuilderError::CaptureGroupAlreadyOpened(offset + other.capture_groups[0].start));
+        }
+
+        for name in other.capture_names.iter().flatten() {
+            self.check_name(name);
+        }
+
+        // Jumps have to be pushed in between the bytes to end up in the same spot
+        let mut jumps = other.jumps.iter().peekable();
+        for index in 0..other.length {
+            while let Some(jump) = jumps.next_if(|j| j.position == index) {
+                self.push_jump(Jump { position: offset + index, ..*jump });
+            }
+
+            self.push(other.bytes[index], other.mask[index]);
+        }
+
+        for jump in jumps {
+            self.push_jump(Jump { position: offset + jump.position, ..*jump });
+        }
+
+        for (index, group) in other.capture_groups.iter().enumerate() {
+            self.pattern.capture_groups.push(offset + group.start..offset + group.end);
+            self.pattern.capture_names.push(other.capture_names.get(index).cloned().flatten());
+        }
+
+        self.pattern.alternatives.extend(other.alternatives.iter().map(|a| Alternatives {
+            position: offset + a.position,
+            values: a.values.clone(),
+        }));
+
+        self
+    }
+
+    /// Finishes the pattern, failing with the first error that occurred while building it.
+    pub fn build(self) -> Result<Pattern, BuilderError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        if let Some((start, _)) = self.open_capture {
+            return Err(BuilderError::CaptureGroupNotClosed(start));
+        }
+
+        let length = self.pattern.length;
+        if self.pattern.jumps.last().is_some_and(|j| j.position == length) {
+            return Err(BuilderError::JumpAtPatternEdge(length));
+        }
+
+        Ok(self.pattern)
+    }
+
+    fn push(&mut self, byte: u8, mask: u8) {
+        // Bits outside of the mask are zeroed the same way the parser does
+        self.pattern.bytes.push(byte & mask);
+        self.pattern.mask.push(mask);
+        self.pattern.length += 1;
+    }
+
+    fn push_jump(&mut self, jump: Jump) {
+        if jump.min > jump.max {
+            return self.fail(BuilderError::InvalidJumpRange(jump.position));
+        }
+
+        if self.open_capture.is_some() {
+            return self.fail(BuilderError::JumpInCaptureGroup(jump.position));
+        }
+
+        if jump.position == 0 {
+            return self.fail(BuilderError::JumpAtPatternEdge(0));
+        }
+
+        match self.pattern.jumps.last_mut() {
+            // Fold consecutive jumps into a single one
+            Some(last) if last.position == jump.position => {
+                last.min += jump.min;
+                last.max += jump.max;
+            },
+            _ => self.pattern.jumps.push(jump),
+        }
+    }
+
+    fn open(&mut self, name: Option<String>) {
+        if self.open_capture.is_some() {
+            return self.fail(BuilderError::CaptureGroupAlreadyOpened(self.pattern.length));
+        }
+
+        if let Some(name) = name.as_deref() {
+            self.check_name(name);
+        }
+
+        self.open_capture = Some((self.pattern.length, name));
+    }
+
+    fn check_name(&mut self, name: &str) {
+        let open = self.open_capture.as_ref().and_then(|(_, n)| n.as_deref());
+        if open == Some(name) || self.pattern.capture_names.iter().flatten().any(|n| n == name) {
+            self.fail(BuilderError::DuplicateCaptureGroupName(name.to_string()));
+        }
+    }
+
+    fn fail(&mut self, error: BuilderError) {
+        self.error.get_or_insert(error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pattern::Pattern;
+    use crate::pattern::builder::BuilderError;
+
+    #[test]
+    fn builder_matches_the_parser() {
+        let rva = Pattern::builder()
+            .bytes(&[0x48, 0x8D, 0x0D])
+            .i32_le(-0x10)
+            .build()
+            .unwrap();
+
+        let call = Pattern::from_byte_pattern("(E8|E9) [target: ?? ?? ?? ??]").unwrap();
+
+        let pattern = Pattern::builder()
+            .concat(&rva)
+            .masked(0x4F, 0xF0)
+            .jump(2, 4)
+            .concat(&call)
+            .begin_capture()
+            .u32_le(0xDEADBEEF)
+            .wildcard(1)
+            .end_capture()
+            .masked(0xFF, 0x0F)
+            .build()
+            .unwrap();
+
+        let parsed = Pattern::from_byte_pattern(
+            "48 8D 0D F0 FF FF FF 4? {2-4} (E8|E9) [target: ?? ?? ?? ??] [EF BE AD DE ??] ?F",
+        ).unwrap();
+
+        assert_eq!(pattern, parsed);
+    }
+
+    #[test]
+    fn builder_folds_jumps_across_concat() {
+        let tail = Pattern::from_byte_pattern("01 {1-2} 02").unwrap();
+        let pattern = Pattern::builder()
+            .bytes(&[0x00])
+            .jump(1, 1)
+            .concat(&tail)
+            .jump(0, 3)
+            .jump(1, 1)
+            .concat(&tail)
+            .build()
+            .unwrap();
+
+        assert_eq!(pattern, Pattern::from_byte_pattern("00 {1} 01 {1-2} 02 {1-4} 01 {1-2} 02").unwrap());
+    }
+
+    #[test]
+    fn builder_validates_capture_groups() {
+        let named = Pattern::from_byte_pattern("[name: ??]").unwrap();
+
+        let nested = Pattern::builder().begin_capture().wildcard(2).begin_capture().build();
+        assert_eq!(nested, Err(BuilderError::CaptureGroupAlreadyOpened(2)));
+
+        let unopened = Pattern::builder().wildcard(1).end_capture().begin_capture().build();
+        assert_eq!(unopened, Err(BuilderError::CaptureGroupNotOpened(1)));
+
+        let unclosed = Pattern::builder().wildcard(1).begin_capture().wildcard(1).build();
+        assert_eq!(unclosed, Err(BuilderError::CaptureGroupNotClosed(1)));
+
+        let nested_concat = Pattern::builder().wildcard(1).begin_capture().concat(&named).end_capture().build();
+        assert_eq!(nested_concat, Err(BuilderError::CaptureGroupAlreadyOpened(1)));
+
+        let duplicate = Pattern::builder().concat(&named).begin_named_capture("name").end_capture().build();
+        assert_eq!(duplicate, Err(BuilderError::DuplicateCaptureGroupName("name".to_string())));
+
+        let invalid = Pattern::builder().begin_named_capture("1st").end_capture().build();
+        assert_eq!(invalid, Err(BuilderError::InvalidCaptureGroupName("1st".to_string())));
+    }
+
+    #[test]
+    fn builder_validates_jumps() {
+        let inside = Pattern::builder().wildcard(1).begin_capture().jump(1, 2).wildcard(1).end_capture().build();
+        assert_eq!(inside, Err(BuilderError::JumpInCaptureGroup(1)));
+
+        assert_eq!(Pattern::builder().jump(1, 2).wildcard(1).build(), Err(BuilderError::JumpAtPatternEdge(0)));
+        assert_eq!(Pattern::builder().wildcard(1).jump(1, 2).build(), Err(BuilderError::JumpAtPatternEdge(1)));
+
+        let inverted = Pattern::builder().wildcard(2).jump(16, 4).wildcard(1).build();
+        assert_eq!(inverted, Err(BuilderError::InvalidJumpRange(2)));
+        assert!(Pattern::from_byte_pattern("?? ?? {16-4} ??").is_err());
+    }
+}
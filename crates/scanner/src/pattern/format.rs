@@ -0,0 +1,190 @@
+use std::fmt;
+use std::mem;
+use std::iter;
+
+use crate::pattern::Pattern;
+
+/// Formats the pattern in the byte pattern syntax. The output is canonical: bytes are uppercase hex
+/// separated by a single space, `??` for fully unmatched bytes, nibble wildcards where only half of
+/// a byte is matched and `m` bits for any other mask. Parsing the output with
+/// `Pattern::from_byte_pattern` results in an identical pattern, for any pattern the syntax can
+/// express.
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut items = Vec::new();
+
+        for position in 0..=self.length {
+            let mut item = String::new();
+
+            // Groups are closed before anything else is placed at the same position
+            for group in self.capture_groups.iter() {
+                if group.end == position && group.start < position {
+                    item.push(']');
+                }
+            }
+
+            if !item.is_empty() {
+                let last = items.pop().unwrap_or_default();
+                items.push(last + item.as_str());
+                item.clear();
+            }
+
+            for jump in self.jumps.iter().filter(|j| j.position == position) {
+                match jump.min == jump.max {
+                    true => items.push(format!("{{{}}}", jump.min)),
+                    false => items.push(format!("{{{}-{}}}", jump.min, jump.max)),
+                }
+            }
+
+            for (index, group) in self.capture_groups.iter().enumerate() {
+                if group.start != position {
+                    continue;
+                }
+
+                item.push('[');
+                if let Some(Some(name)) = self.capture_names.get(index) {
+                    item.push_str(name);
+                    item.push_str(": ");
+                }
+
+                if group.is_empty() {
+                    item.push(']');
+                    items.push(mem::take(&mut item));
+                }
+            }
+
+            if position < self.length {
+                item.push_str(&self.format_position(position));
+                items.push(item);
+            } else if !item.is_empty() {
+                items.push(item);
+            }
+        }
+
+        write!(f, "{}", items.join(" "))
+    }
+}
+
+impl Pattern {
+    /// Formats the byte at `position` along with its alternatives.
+    fn format_position(&self, position: usize) -> String {
+        let alternatives = self.alternatives_at(position);
+        if alternatives.is_empty() {
+            return format_byte(self.bytes[position], self.mask[position]);
+        }
+
+        let values = iter::once(format_byte(self.bytes[position], self.mask[position]))
+            .chain(alternatives.iter().map(|(b, m)| format_byte(*b, *m)))
+            .collect::<Vec<String>>();
+
+        format!("({})", values.join("|"))
+    }
+}
+
+fn format_byte(byte: u8, mask: u8) -> String {
+    match mask {
+        0xFF => format!("{byte:02X}"),
+        0x00 => "??".to_string(),
+        0xF0 => format!("{:X}?", byte >> 4),
+        0x0F => format!("?{:X}", byte & 0x0F),
+        _ => {
+            let bits = (0..8).rev()
+                .map(|i| match (mask >> i & 1, byte >> i & 1) {
+                    (0, _) => '?',
+                    (_, 0) => '0',
+                    _ => '1',
+                })
+                .collect::<String>();
+
+            format!("m{bits}")
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use crate::pattern::{Alternatives, Jump, Pattern};
+
+    #[test]
+    fn pattern_formats_canonically() {
+        let pattern = Pattern::from_byte_pattern(
+            "48 8b 0d [vtable:?? ??  ?? ??] e8 {4-16} (e8|E9|4?) ?f m0101?..? m01.1.0.1 {2} [] [ab]"
+        ).unwrap();
+
+        assert_eq!(
+            pattern.to_string(),
+            "48 8B 0D [vtable: ?? ?? ?? ??] E8 {4-16} (E8|E9|4?) ?F 5? m01?1?0?1 {2} [] [AB]",
+        );
+    }
+
+    #[test]
+    fn bit_pattern_formats_as_byte_pattern() {
+        let pattern = Pattern::from_bit_pattern("[00010010 ....0000] 1111.111").unwrap();
+
+        assert_eq!(pattern.to_string(), "[12 ?0] m1111?111");
+    }
+
+    #[test]
+    fn pattern_round_trips_through_formatting() {
+        let mut rng = StdRng::seed_from_u64(0x666f726d6174);
+
+        for _ in 0..500 {
+            let pattern = random_pattern(&mut rng);
+            let formatted = pattern.to_string();
+            let parsed = Pattern::from_byte_pattern(&formatted)
+                .unwrap_or_else(|e| panic!("{}", e.render(&formatted)));
+
+            assert_eq!(parsed, pattern, "{formatted} did not round-trip");
+        }
+    }
+
+    /// Builds a random pattern out of everything the byte pattern syntax can express.
+    fn random_pattern(rng: &mut StdRng) -> Pattern {
+        let length = rng.gen_range(1..24);
+        let mut pattern = Pattern::from_byte_vec(vec![0; length]);
+
+        let random_byte = |rng: &mut StdRng| {
+            let mask = match rng.gen_range(0..5) {
+                0 => 0x00,
+                1 => 0xF0,
+                2 => 0x0F,
+                3 => rng.gen(),
+                _ => 0xFF,
+            };
+
+            (rng.gen::<u8>() & mask, mask)
+        };
+
+        for i in 0..length {
+            (pattern.bytes[i], pattern.mask[i]) = random_byte(rng);
+
+            if rng.gen_bool(0.1) {
+                let values = (0..rng.gen_range(1..3)).map(|_| random_byte(rng)).collect();
+                pattern.alternatives.push(Alternatives { position: i, values });
+            }
+        }
+
+        // Captures and jumps alternate so jumps never end up inside of a capture group
+        let mut position = 0;
+        while position < length {
+            let start = rng.gen_range(position..=length);
+            let end = rng.gen_range(start..=length);
+            if rng.gen_bool(0.5) {
+                pattern.capture_groups.push(start..end);
+                pattern.capture_names.push(rng.gen_bool(0.5).then(|| format!("capture_{start}")));
+            }
+
+            if end > 0 && end < length && rng.gen_bool(0.5) {
+                let min = rng.gen_range(0..8);
+                pattern.jumps.push(Jump { position: end, min, max: rng.gen_range(min..16) });
+            }
+
+            position = end + 1;
+        }
+
+        pattern
+    }
+}
@@ -0,0 +1,381 @@
+use std::fmt;
+use std::error::Error;
+
+use crate::pattern::Pattern;
+use crate::pattern::parser::{ParserError, ParserResult};
+use crate::pattern::tokenizer::{Span, TokenizationError};
+
+/// Reasons a pattern can't be written in another tool's format. Capture groups are dropped when
+/// exporting as none of the formats have them, this doesn't change what the pattern matches.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ExportError {
+    /// The pattern has a jump in front of the byte at the position.
+    Jump(usize),
+    /// The byte at the position has alternatives.
+    Alternatives(usize),
+    /// The byte at the position has a mask the format can't express.
+    Mask(usize),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Jump(position) => write!(f, "jump in front of byte {position} cannot be exported"),
+            Self::Alternatives(position) => write!(f, "alternatives of byte {position} cannot be exported"),
+            Self::Mask(position) => write!(f, "mask of byte {position} cannot be exported"),
+        }
+    }
+}
+
+impl Error for ExportError {}
+
+/// Reasons a code style signature can't be parsed. The spans of the parser errors point into the
+/// string the variant is named after.
+#[derive(Debug, PartialEq, Eq)]
+pub enum CodeStyleError {
+    Bytes(ParserError),
+    Mask(ParserError),
+    /// The mask doesn't have a character for every byte, holds the amount of bytes and mask
+    /// characters.
+    MaskLengthMismatch(usize, usize),
+}
+
+impl fmt::Display for CodeStyleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bytes(e) => write!(f, "invalid bytes: {e}"),
+            Self::Mask(e) => write!(f, "invalid mask: {e}"),
+            Self::MaskLengthMismatch(bytes, mask) => write!(f, "mask has {mask} characters for {bytes} bytes"),
+        }
+    }
+}
+
+impl Error for CodeStyleError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Bytes(e) | Self::Mask(e) => Some(e),
+            Self::MaskLengthMismatch(..) => None,
+        }
+    }
+}
+
+impl Pattern {
+    /// Parses an IDA style signature, like `48 8B ? ? 05`. Wildcards are written as `?` or `??`.
+    pub fn from_ida(pattern: &str) -> ParserResult {
+        let bytes = words(pattern)
+            .map(|(offset, word)| match word {
+                "?" | "??" => Ok((0x00, 0x00)),
+                _ => parse_byte(pattern, offset, word, false),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(from_masked_bytes(bytes))
+    }
+
+    /// Writes the pattern as an IDA style signature.
+    pub fn to_ida(&self) -> Result<String, ExportError> {
+        self.export(" ", |byte, mask| match mask {
+            0xFF => Some(format!("{byte:02X}")),
+            0x00 => Some("?".to_string()),
+            _ => None,
+        })
+    }
+
+    /// Parses a Cheat Engine array of bytes, like `48 8B * * 05`. Wildcards are written as `*` or
+    /// `?`, either for the entire byte or for a single nibble.
+    pub fn from_ce_aob(pattern: &str) -> ParserResult {
+        let bytes = words(pattern)
+            .map(|(offset, word)| match word {
+                "*" | "?" | "**" | "??" => Ok((0x00, 0x00)),
+                _ => parse_byte(pattern, offset, word, true),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(from_masked_bytes(bytes))
+    }
+
+    /// Writes the pattern as a Cheat Engine array of bytes.
+    pub fn to_ce_aob(&self) -> Result<String, ExportError> {
+        self.export(" ", |byte, mask| match mask {
+            0xFF => Some(format!("{byte:02X}")),
+            0x00 => Some("*".to_string()),
+            0xF0 => Some(format!("{:X}*", byte >> 4)),
+            0x0F => Some(format!("*{:X}", byte & 0x0F)),
+            _ => None,
+        })
+    }
+
+    /// Parses an x64dbg style pattern, like `488B????05`. Every nibble is either a hex digit or a
+    /// `?`, whitespace is ignored.
+    pub fn from_x64dbg(pattern: &str) -> ParserResult {
+        let nibbles = pattern.char_indices()
+            .filter(|(_, c)| !c.is_whitespace())
+            .collect::<Vec<_>>();
+
+        // Checked up front as whitespace can end up in between the nibbles of a byte
+        if let Some((offset, _)) = nibbles.iter().find(|(_, c)| !c.is_ascii_hexdigit() && *c != '?') {
+            return Err(unknown_input(pattern, *offset));
+        }
+
+        let bytes = nibbles.chunks(2)
+            .map(|chunk| match chunk {
+                [(offset, high), (_, low)] => {
+                    let word = [*high, *low].iter().collect::<String>();
+                    parse_byte(pattern, *offset, &word, true)
+                },
+                [(offset, _)] => Err(incomplete_byte(pattern, *offset)),
+                _ => unreachable!(),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(from_masked_bytes(bytes))
+    }
+
+    /// Writes the pattern as an x64dbg style pattern.
+    pub fn to_x64dbg(&self) -> Result<String, ExportError> {
+        self.export("", |byte, mask| match mask {
+            0xFF => Some(format!("{byte:02X}")),
+            0x00 => Some("??".to_string()),
+            0xF0 => Some(format!("{:X}?", byte >> 4)),
+            0x0F => Some(format!("?{:X}", byte & 0x0F)),
+            _ => None,
+        })
+    }
+
+    /// Parses a code style signature, where the bytes are written as escapes like `\x48\x8B\x00\x05`
+    /// and the mask as `xx?x`. Bytes are matched where the mask has an `x` and skipped where it
+    /// has a `?`. Both strings may still be surrounded by quotes.
+    pub fn from_code_style(bytes: &str, mask: &str) -> Result<Pattern, CodeStyleError> {
+        let byte_values = parse_escaped_bytes(bytes).map_err(CodeStyleError::Bytes)?;
+
+        let (mask_offset, mask_chars) = strip_quotes(mask);
+        if byte_values.len() != mask_chars.chars().count() {
+            return Err(CodeStyleError::MaskLengthMismatch(byte_values.len(), mask_chars.chars().count()));
+        }
+
+        let bytes = byte_values.into_iter()
+            .zip(mask_chars.char_indices())
+            .map(|(byte, (offset, c))| match c {
+                'x' | 'X' => Ok((byte, 0xFF)),
+                '?' => Ok((0x00, 0x00)),
+                _ => Err(CodeStyleError::Mask(unknown_input(mask, mask_offset + offset))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(from_masked_bytes(bytes))
+    }
+
+    /// Writes the pattern as a code style signature, returning the escaped bytes and the mask.
+    /// Wildcarded bytes are written as `\x00`.
+    pub fn to_code_style(&self) -> Result<(String, String), ExportError> {
+        let bytes = self.export("", |byte, mask| match mask {
+            0xFF => Some(format!("\\x{byte:02X}")),
+            0x00 => Some("\\x00".to_string()),
+            _ => None,
+        })?;
+
+        let mask = self.mask.iter()
+            .map(|m| if *m == 0xFF { 'x' } else { '?' })
+            .collect();
+
+        Ok((bytes, mask))
+    }
+
+    /// Formats every byte with `format_byte` and joins them with the separator. Fails on anything
+    /// other than plain bytes with a mask `format_byte` accepts.
+    fn export(
+        &self,
+        separator: &str,
+        format_byte: impl Fn(u8, u8) -> Option<String>,
+    ) -> Result<String, ExportError> {
+        if let Some(jump) = self.jumps.first() {
+            return Err(ExportError::Jump(jump.position));
+        }
+
+        if let Some(alternatives) = self.alternatives.first() {
+            return Err(ExportError::Alternatives(alternatives.position));
+        }
+
+        let bytes = self.bytes.iter()
+            .zip(self.mask.iter())
+            .enumerate()
+            .map(|(i, (byte, mask))| format_byte(*byte, *mask).ok_or(ExportError::Mask(i)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(bytes.join(separator))
+    }
+}
+
+fn from_masked_bytes(bytes: Vec<(u8, u8)>) -> Pattern {
+    let (bytes, mask): (Vec<u8>, Vec<u8>) = bytes.into_iter().unzip();
+
+    let mut pattern = Pattern::from_byte_vec(bytes);
+    pattern.mask = mask;
+    pattern
+}
+
+/// Whitespace separated words along with their offset in the input.
+fn words(input: &str) -> impl Iterator<Item = (usize, &str)> {
+    input.split_whitespace()
+        .map(move |word| (word.as_ptr() as usize - input.as_ptr() as usize, word))
+}
+
+/// Parses a two character hex byte. With `nibble_wildcards` either character can be a wildcard,
+/// Cheat Engine writes these as `*` while others use `?`.
+fn parse_byte(input: &str, offset: usize, word: &str, nibble_wildcards: bool) -> Result<(u8, u8), ParserError> {
+    let mut chars = word.char_indices();
+    let (Some(high), Some(low)) = (chars.next(), chars.next()) else {
+        return Err(incomplete_byte(input, offset));
+    };
+
+    if let Some((i, _)) = chars.next() {
+        return Err(unknown_input(input, offset + i));
+    }
+
+    let mut byte = 0;
+    let mut mask = 0;
+    for (i, c) in [high, low] {
+        byte <<= 4;
+        mask <<= 4;
+
+        match c.to_digit(16) {
+            Some(value) => {
+                byte |= value as u8;
+                mask |= 0xF;
+            },
+            None if nibble_wildcards && matches!(c, '?' | '*') => {},
+            None => return Err(unknown_input(input, offset + i)),
+        }
+    }
+
+    Ok((byte, mask))
+}
+
+/// Parses bytes written as `\x48\x8B`, optionally in between quotes.
+fn parse_escaped_bytes(input: &str) -> Result<Vec<u8>, ParserError> {
+    let (start, escaped) = strip_quotes(input);
+
+    let mut bytes = Vec::new();
+    let mut rest = escaped;
+    while !rest.is_empty() {
+        let offset = start + escaped.len() - rest.len();
+        let Some(hex) = rest.strip_prefix("\\x") else {
+            return Err(unknown_input(input, offset));
+        };
+
+        let digits = hex.get(..2)
+            .filter(|d| d.chars().all(|c| c.is_ascii_hexdigit()))
+            .ok_or_else(|| incomplete_byte(input, offset))?;
+
+        bytes.push(u8::from_str_radix(digits, 16).unwrap());
+        rest = &hex[2..];
+    }
+
+    Ok(bytes)
+}
+
+/// Trims whitespace and surrounding quotes, returns what's left along with its offset in the input.
+fn strip_quotes(input: &str) -> (usize, &str) {
+    let trimmed = input.trim();
+    let trimmed = match trimmed.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+        Some(unquoted) => unquoted,
+        None => trimmed,
+    };
+
+    (trimmed.as_ptr() as usize - input.as_ptr() as usize, trimmed)
+}
+
+fn unknown_input(input: &str, offset: usize) -> ParserError {
+    ParserError::Tokenizer(TokenizationError::UnknownInput(Span::at(input, offset)))
+}
+
+fn incomplete_byte(input: &str, offset: usize) -> ParserError {
+    ParserError::Tokenizer(TokenizationError::IncompleteByte(Span::at(input, offset)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pattern::{CodeStyleError, ExportError, Pattern};
+    use crate::pattern::parser::ParserError;
+    use crate::pattern::tokenizer::{Span, TokenizationError};
+
+    #[test]
+    fn community_formats_parse_to_the_same_pattern() {
+        let expected = Pattern::from_byte_pattern("48 8B ?? ?? 05").unwrap();
+
+        assert_eq!(Pattern::from_ida("48 8B ? ?? 05").unwrap(), expected);
+        assert_eq!(Pattern::from_ce_aob("48 8b * ?? 05").unwrap(), expected);
+        assert_eq!(Pattern::from_x64dbg("488B ????05").unwrap(), expected);
+        assert_eq!(Pattern::from_code_style(r#""\x48\x8B\x00\x00\x05""#, r#""xx??x""#).unwrap(), expected);
+        assert_eq!(Pattern::from_code_style(r"\x48\x8b\xCC\xCC\x05", "xx??x").unwrap(), expected);
+    }
+
+    #[test]
+    fn community_formats_support_nibble_wildcards() {
+        let expected = Pattern::from_byte_pattern("48 ?B 0? 05").unwrap();
+
+        assert_eq!(Pattern::from_ce_aob("48 *B 0* 05").unwrap(), expected);
+        assert_eq!(Pattern::from_x64dbg("48?B0?05").unwrap(), expected);
+        assert!(Pattern::from_ida("48 ?B 0? 05").is_err());
+    }
+
+    #[test]
+    fn community_formats_export() {
+        let pattern = Pattern::from_byte_pattern("48 8B [?? ??] 05").unwrap();
+
+        assert_eq!(pattern.to_ida().unwrap(), "48 8B ? ? 05");
+        assert_eq!(pattern.to_ce_aob().unwrap(), "48 8B * * 05");
+        assert_eq!(pattern.to_x64dbg().unwrap(), "488B????05");
+        assert_eq!(
+            pattern.to_code_style().unwrap(),
+            (r"\x48\x8B\x00\x00\x05".to_string(), "xx??x".to_string()),
+        );
+
+        let nibbles = Pattern::from_byte_pattern("4? 8B").unwrap();
+        assert_eq!(nibbles.to_x64dbg().unwrap(), "4?8B");
+        assert_eq!(nibbles.to_ce_aob().unwrap(), "4* 8B");
+        assert_eq!(nibbles.to_ida(), Err(ExportError::Mask(0)));
+        assert_eq!(nibbles.to_code_style(), Err(ExportError::Mask(0)));
+    }
+
+    #[test]
+    fn community_formats_reject_what_they_cannot_express() {
+        let jump = Pattern::from_byte_pattern("48 {2-4} 8B").unwrap();
+        assert_eq!(jump.to_ida(), Err(ExportError::Jump(1)));
+
+        let alternatives = Pattern::from_byte_pattern("48 (8B|89)").unwrap();
+        assert_eq!(alternatives.to_x64dbg(), Err(ExportError::Alternatives(1)));
+    }
+
+    #[test]
+    fn community_formats_report_errors_with_spans() {
+        assert_eq!(
+            Pattern::from_ida("48 8B ?? XX"),
+            Err(ParserError::Tokenizer(TokenizationError::UnknownInput(
+                Span { offset: 9, character: Some('X') },
+            ))),
+        );
+        assert_eq!(
+            Pattern::from_x64dbg("48 8B 0"),
+            Err(ParserError::Tokenizer(TokenizationError::IncompleteByte(
+                Span { offset: 6, character: Some('0') },
+            ))),
+        );
+        assert_eq!(
+            Pattern::from_code_style(r"\x48\x8B", "x."),
+            Err(CodeStyleError::Mask(ParserError::Tokenizer(TokenizationError::UnknownInput(
+                Span { offset: 1, character: Some('.') },
+            )))),
+        );
+        assert_eq!(
+            Pattern::from_code_style(r"\x48\x8Z", "xx"),
+            Err(CodeStyleError::Bytes(ParserError::Tokenizer(TokenizationError::IncompleteByte(
+                Span { offset: 4, character: Some('\\') },
+            )))),
+        );
+        assert_eq!(
+            Pattern::from_code_style(r"\x48\x8B", "xxx"),
+            Err(CodeStyleError::MaskLengthMismatch(2, 3)),
+        );
+    }
+}
@@ -1,14 +1,48 @@
 use std::ops::Range;
 
+pub mod set;
+pub mod builder;
+mod format;
+mod interop;
+#[cfg(feature = "serde")]
+mod serialize;
 pub mod parser;
 pub mod tokenizer;
 
-#[derive(Debug, Clone)]
+pub use set::PatternSet;
+pub use builder::{BuilderError, PatternBuilder};
+pub use interop::{CodeStyleError, ExportError};
+
+/// Skips a variable amount of bytes, anywhere from `min` up to and including `max`, in front of
+/// the byte at `position` in the pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Jump {
+    pub position: usize,
+    pub min: usize,
+    pub max: usize,
+}
+
+/// Values the byte at `position` is accepted as on top of the one in the pattern's `bytes` and
+/// `mask`. Every value is a byte paired with its mask.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alternatives {
+    pub position: usize,
+    pub values: Vec<(u8, u8)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Pattern {
     pub bytes: Vec<u8>,
     pub mask: Vec<u8>,
     pub length: usize,
     pub capture_groups: Vec<Range<usize>>,
+    /// Names of the capture groups, by the index of the group. `None` for unnamed groups.
+    pub capture_names: Vec<Option<String>>,
+    /// Variable-length gaps between the bytes, ordered by position. Jumps never sit at the start or
+    /// end of a pattern and never inside of a capture group. `length` doesn't include them.
+    pub jumps: Vec<Jump>,
+    /// Extra values accepted for some of the bytes, ordered by position.
+    pub alternatives: Vec<Alternatives>,
 }
 
 impl Pattern {
@@ -17,6 +51,11 @@ impl Pattern {
         parser::parse_pattern(pattern, tokenizer::tokenize_byte_pattern)
     }
 
+    /// Starts building a pattern in code instead of parsing one from a string.
+    pub fn builder() -> PatternBuilder {
+        PatternBuilder::new()
+    }
+
     pub fn from_bit_pattern(pattern: &str) -> parser::ParserResult {
         parser::parse_pattern(pattern, tokenizer::tokenize_bit_pattern)
     }
@@ -27,21 +66,161 @@ impl Pattern {
         let length = bytes.len();
         let mask = vec![0xFFu8; length];
         let capture_groups = vec![];
+        let capture_names = vec![];
+        let jumps = vec![];
+        let alternatives = vec![];
 
-        Self { bytes, mask, length, capture_groups }
+        Self { bytes, mask, length, capture_groups, capture_names, jumps, alternatives }
     }
 
     /// Wraps `from_byte_vec` and copies the `bytes` slice.
     pub fn from_byte_slice(bytes: &[u8]) -> Self {
         Self::from_byte_vec(bytes.to_vec())
     }
+
+    /// Looks up the index of a capture group by its name.
+    pub fn capture_index(&self, name: &str) -> Result<usize, parser::ParserError> {
+        self.capture_names.iter()
+            .position(|n| n.as_deref() == Some(name))
+            .ok_or_else(|| parser::ParserError::UnknownCaptureGroupName(name.to_string()))
+    }
+
+    /// Amount of bytes the shortest possible match spans.
+    pub fn min_length(&self) -> usize {
+        self.length + self.jumps.iter().map(|j| j.min).sum::<usize>()
+    }
+
+    /// Amount of bytes the longest possible match spans.
+    pub fn max_length(&self) -> usize {
+        self.length + self.jumps.iter().map(|j| j.max).sum::<usize>()
+    }
+
+    /// Checks if the pattern matches the start of `bytes` by doing a masked compare of every byte.
+    pub fn matches(&self, bytes: &[u8]) -> bool {
+        if self.jumps.is_empty() {
+            return self.matches_range(bytes, 0..self.length);
+        }
+
+        self.jump_lengths(bytes).is_some()
+    }
+
+    /// Matches the pattern against the start of `bytes` and returns the amount of bytes every jump
+    /// skipped. Shorter jumps are tried first.
+    pub(crate) fn jump_lengths(&self, bytes: &[u8]) -> Option<Vec<usize>> {
+        let mut lengths = Vec::with_capacity(self.jumps.len());
+
+        self.match_from(bytes, 0, &mut lengths)
+            .then_some(lengths)
+    }
+
+    /// Amount of bytes spanned by the match at the start of `bytes`.
+    pub(crate) fn match_length(&self, bytes: &[u8]) -> usize {
+        if self.jumps.is_empty() {
+            return self.length;
+        }
+
+        self.jump_lengths(bytes)
+            .map(|l| self.length + l.iter().sum::<usize>())
+            .unwrap_or(self.length)
+    }
+
+    /// Translates an index in the pattern's bytes to an offset in a match, given the amount of
+    /// bytes every jump skipped in that match.
+    pub(crate) fn offset_in_match(&self, index: usize, jump_lengths: &[usize]) -> usize {
+        index + self.jumps.iter()
+            .zip(jump_lengths.iter())
+            .filter(|(j, _)| j.position <= index)
+            .map(|(_, l)| l)
+            .sum::<usize>()
+    }
+
+    /// Amount of bytes in front of the first jump.
+    pub(crate) fn prefix_length(&self) -> usize {
+        self.jumps.first()
+            .map(|j| j.position)
+            .unwrap_or(self.length)
+    }
+
+    /// The bytes in front of the first jump as a pattern of their own.
+    pub(crate) fn prefix(&self) -> Pattern {
+        let length = self.prefix_length();
+
+        Pattern {
+            bytes: self.bytes[..length].to_vec(),
+            mask: self.mask[..length].to_vec(),
+            length,
+            capture_groups: vec![],
+            capture_names: vec![],
+            jumps: vec![],
+            alternatives: self.alternatives.iter()
+                .filter(|a| a.position < length)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Checks if `value` is accepted for the byte at `index`, either through the masked byte or
+    /// any of the alternatives.
+    pub(crate) fn accepts(&self, index: usize, value: u8) -> bool {
+        value & self.mask[index] == self.bytes[index]
+            || self.alternatives_at(index).iter().any(|(b, m)| value & m == *b)
+    }
+
+    /// The extra values accepted for the byte at `index`.
+    pub(crate) fn alternatives_at(&self, index: usize) -> &[(u8, u8)] {
+        self.alternatives.binary_search_by_key(&index, |a| a.position)
+            .map(|i| self.alternatives[i].values.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Matches the bytes from `start` up to the next jump against `bytes`, then tries every length
+    /// of that jump for the rest of the pattern. `lengths` holds the lengths picked so far.
+    fn match_from(&self, bytes: &[u8], start: usize, lengths: &mut Vec<usize>) -> bool {
+        let jump = self.jumps.get(lengths.len());
+        let end = jump.map(|j| j.position).unwrap_or(self.length);
+
+        if !self.matches_range(bytes, start..end) {
+            return false;
+        }
+
+        let Some(jump) = jump else {
+            return true;
+        };
+
+        let rest = &bytes[end - start..];
+        for length in jump.min..=jump.max.min(rest.len()) {
+            lengths.push(length);
+            if self.match_from(&rest[length..], end, lengths) {
+                return true;
+            }
+
+            lengths.pop();
+        }
+
+        false
+    }
+
+    fn matches_range(&self, bytes: &[u8], range: Range<usize>) -> bool {
+        if bytes.len() < range.len() {
+            return false;
+        }
+
+        if !self.alternatives.is_empty() {
+            return range.zip(bytes.iter()).all(|(i, byte)| self.accepts(i, *byte));
+        }
+
+        bytes.iter()
+            .zip(self.bytes[range.clone()].iter().zip(self.mask[range].iter()))
+            .all(|(byte, (expected, mask))| *expected == *byte & *mask)
+    }
 }
 
 #[cfg(test)]
 #[allow(clippy::bool_assert_comparison)]
 mod tests {
-    use crate::pattern::Pattern;
+    use crate::pattern::{Alternatives, Jump, Pattern};
     use crate::pattern::parser::ParserError;
+    use crate::pattern::tokenizer::Span;
 
     #[test]
     fn byte_pattern_works() {
@@ -148,7 +327,7 @@ mod tests {
 
         assert!(matches!(
             result.unwrap_err(),
-            ParserError::CaptureGroupAlreadyOpened
+            ParserError::CaptureGroupAlreadyOpened(_)
         ));
     }
 
@@ -158,10 +337,157 @@ mod tests {
 
         assert!(matches!(
             result.unwrap_err(),
-            ParserError::CaptureGroupNotOpened
+            ParserError::CaptureGroupNotOpened(_)
         ));
     }
 
+    #[test]
+    fn byte_pattern_names_capture_groups() {
+        let pattern = Pattern::from_byte_pattern("48 8B 0D [vtable: ?? ?? ?? ??] [??] E8 [Target: ?? ?? ?? ??]").unwrap();
+
+        assert_eq!(pattern.capture_groups, vec![3..7, 7..8, 9..13]);
+        assert_eq!(pattern.capture_names, vec![Some("vtable".to_string()), None, Some("Target".to_string())]);
+        assert_eq!(pattern.capture_index("vtable").unwrap(), 0);
+        assert_eq!(pattern.capture_index("Target").unwrap(), 2);
+        assert!(matches!(
+            pattern.capture_index("target").unwrap_err(),
+            ParserError::UnknownCaptureGroupName(name) if name == "target"
+        ));
+    }
+
+    #[test]
+    fn bit_pattern_names_capture_groups() {
+        let pattern = Pattern::from_bit_pattern("[flags: 0000....] 11111111").unwrap();
+
+        assert_eq!(pattern.capture_groups, vec![0..1]);
+        assert_eq!(pattern.capture_names, vec![Some("flags".to_string())]);
+    }
+
+    #[test]
+    fn byte_pattern_returns_error_on_duplicate_capture_group_name() {
+        let result = Pattern::from_byte_pattern("[a: 12] 34 [a: 56]");
+
+        assert!(matches!(
+            result.unwrap_err(),
+            ParserError::DuplicateCaptureGroupName(name, _) if name == "a"
+        ));
+    }
+
+    #[test]
+    fn byte_pattern_reads_jumps() {
+        let pattern = Pattern::from_byte_pattern("E8 {4-16} [C3] {2} {0-1} ?? 90").unwrap();
+
+        assert_eq!(pattern.length, 4);
+        assert_eq!(pattern.bytes, vec![0xE8, 0xC3, 0x00, 0x90]);
+        assert_eq!(pattern.capture_groups, vec![1..2]);
+        assert_eq!(pattern.jumps, vec![
+            Jump { position: 1, min: 4, max: 16 },
+            Jump { position: 2, min: 2, max: 3 },
+        ]);
+        assert_eq!(pattern.min_length(), 10);
+        assert_eq!(pattern.max_length(), 23);
+    }
+
+    #[test]
+    fn byte_pattern_returns_error_on_misplaced_jumps() {
+        assert!(matches!(
+            Pattern::from_byte_pattern("E8 [?? {4-16} ??]").unwrap_err(),
+            ParserError::JumpInCaptureGroup(_)
+        ));
+        assert!(matches!(
+            Pattern::from_byte_pattern("{4-16} E8").unwrap_err(),
+            ParserError::JumpAtPatternEdge(_)
+        ));
+        assert!(matches!(
+            Pattern::from_byte_pattern("E8 {4-16}").unwrap_err(),
+            ParserError::JumpAtPatternEdge(_)
+        ));
+    }
+
+    #[test]
+    fn pattern_matches_across_jumps() {
+        let pattern = Pattern::from_byte_pattern("01 {0-2} 02 {1-2} 03").unwrap();
+
+        assert_eq!(pattern.jump_lengths(&[0x01, 0x02, 0xFF, 0x03]), Some(vec![0, 1]));
+        assert_eq!(pattern.jump_lengths(&[0x01, 0xFF, 0x02, 0x03, 0xFF, 0x03]), Some(vec![1, 2]));
+        // The first jump has to backtrack when the shortest option doesn't work out later on
+        assert_eq!(pattern.jump_lengths(&[0x01, 0x02, 0x02, 0xFF, 0xFF, 0x03]), Some(vec![1, 2]));
+        assert_eq!(pattern.jump_lengths(&[0x01, 0x02, 0x03]), None);
+        assert_eq!(pattern.jump_lengths(&[0x01, 0xFF, 0xFF, 0xFF, 0x02, 0xFF, 0x03]), None);
+        assert!(pattern.matches(&[0x01, 0x02, 0xFF, 0x03]));
+        assert!(!pattern.matches(&[0x01, 0x02, 0xFF]));
+    }
+
+    #[test]
+    fn byte_pattern_reads_alternatives() {
+        let pattern = Pattern::from_byte_pattern("(E8|E9) [4? ?F] (48|4C|5?) (90)").unwrap();
+
+        assert_eq!(pattern.length, 5);
+        assert_eq!(pattern.bytes, vec![0xE8, 0x40, 0x0F, 0x48, 0x90]);
+        assert_eq!(pattern.mask, vec![0xFF, 0xF0, 0x0F, 0xFF, 0xFF]);
+        assert_eq!(pattern.capture_groups, vec![1..3]);
+        assert_eq!(pattern.alternatives, vec![
+            Alternatives { position: 0, values: vec![(0xE9, 0xFF)] },
+            Alternatives { position: 3, values: vec![(0x4C, 0xFF), (0x50, 0xF0)] },
+        ]);
+
+        assert!(pattern.matches(&[0xE8, 0x41, 0x1F, 0x48, 0x90]));
+        assert!(pattern.matches(&[0xE9, 0x4F, 0xFF, 0x4C, 0x90]));
+        assert!(pattern.matches(&[0xE9, 0x4F, 0xFF, 0x5A, 0x90]));
+        assert!(!pattern.matches(&[0xEA, 0x4F, 0xFF, 0x4C, 0x90]));
+        assert!(!pattern.matches(&[0xE8, 0x41, 0x1F, 0x49, 0x90]));
+        assert!(!pattern.matches(&[0xE8, 0x51, 0x1F, 0x48, 0x90]));
+    }
+
+    #[test]
+    fn byte_pattern_reads_strings() {
+        let pattern = Pattern::from_byte_pattern("[name: i\"param\"] 00 u\"ok\"").unwrap();
+
+        assert_eq!(pattern.length, 10);
+        assert_eq!(pattern.capture_groups, vec![0..5]);
+        assert!(pattern.matches(b"param\0o\0k\0"));
+        assert!(pattern.matches(b"PaRaM\0o\0k\0"));
+        assert!(!pattern.matches(b"param\0ok\0\0"));
+        assert!(!pattern.matches(b"p@ram\0o\0k\0"));
+        assert_eq!(pattern.to_string(), "[name: m01?10000 m01?00001 m01?10010 m01?00001 m01?01101] 00 6F 00 6B 00");
+    }
+
+    #[test]
+    fn byte_pattern_errors_point_at_the_offending_character() {
+        let result = Pattern::from_byte_pattern("12 34]");
+        assert_eq!(result.unwrap_err().span(), Some(Span { offset: 5, character: Some(']') }));
+
+        let result = Pattern::from_byte_pattern("12 [34 56");
+        assert_eq!(result.unwrap_err().span(), Some(Span { offset: 3, character: Some('[') }));
+
+        let result = Pattern::from_byte_pattern("[a: 12] [ a: 34]");
+        assert_eq!(result.unwrap_err().span(), Some(Span { offset: 10, character: Some('a') }));
+
+        let result = Pattern::from_byte_pattern("12 34 {4}");
+        assert_eq!(result.unwrap_err().span(), Some(Span { offset: 6, character: Some('{') }));
+    }
+
+    #[test]
+    fn parser_errors_render_a_caret() {
+        let input = "48 8B 05 [?? ?? ?? ??]\n\u{e9}8 C3";
+        let error = Pattern::from_byte_pattern(input).unwrap_err();
+
+        assert_eq!(
+            error.render(input),
+            "unexpected input at offset 23 ('\u{e9}')\n\u{e9}8 C3\n^",
+        );
+
+        let input = "48 8B 05 [?? ?? ?? ??] E8 ?? ?? ?? ??]";
+        let error = Pattern::from_byte_pattern(input).unwrap_err();
+
+        assert_eq!(
+            error.render(input),
+            "capture group closed at offset 37 (']') was never opened\n\
+             48 8B 05 [?? ?? ?? ??] E8 ?? ?? ?? ??]\n\
+             \x20                                    ^",
+        );
+    }
+
     #[test]
     fn bit_pattern_returns_error_on_invalid_bit_value() {
         let result = Pattern::from_bit_pattern("00000002 00000000");
@@ -178,7 +504,7 @@ mod tests {
 
         assert!(matches!(
             result.unwrap_err(),
-            ParserError::CaptureGroupAlreadyOpened
+            ParserError::CaptureGroupAlreadyOpened(_)
         ));
     }
 
@@ -188,7 +514,7 @@ mod tests {
 
         assert!(matches!(
             result.unwrap_err(),
-            ParserError::CaptureGroupNotOpened
+            ParserError::CaptureGroupNotOpened(_)
         ));
     }
 }
@@ -1,15 +1,100 @@
+use std::fmt;
+use std::error::Error;
 use std::ops::Range;
-use crate::pattern::Pattern;
+use crate::pattern::{Alternatives, Jump, Pattern};
 use crate::pattern::tokenizer;
+use crate::pattern::tokenizer::Span;
 
 use super::tokenizer::TokenizerFn;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub enum ParserError {
     Tokenizer(tokenizer::TokenizationError),
-    CaptureGroupAlreadyOpened,
-    CaptureGroupNotOpened,
-    CaptureGroupNotClosed,
+    CaptureGroupAlreadyOpened(Span),
+    CaptureGroupNotOpened(Span),
+    /// The span points at where the unclosed capture group was opened.
+    CaptureGroupNotClosed(Span),
+    /// Two capture groups in the pattern share the same name.
+    DuplicateCaptureGroupName(String, Span),
+    /// A capture group was looked up by a name that isn't in the pattern.
+    UnknownCaptureGroupName(String),
+    /// A jump was placed inside of a capture group, which would give the capture a variable width.
+    JumpInCaptureGroup(Span),
+    /// A jump was placed at the start or end of the pattern where it has nothing to skip between.
+    JumpAtPatternEdge(Span),
+}
+
+impl ParserError {
+    /// Where in the pattern string the error occurred. Lookups by name don't have one.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::Tokenizer(e) => Some(e.span()),
+            Self::CaptureGroupAlreadyOpened(span)
+            | Self::CaptureGroupNotOpened(span)
+            | Self::CaptureGroupNotClosed(span)
+            | Self::DuplicateCaptureGroupName(_, span)
+            | Self::JumpInCaptureGroup(span)
+            | Self::JumpAtPatternEdge(span) => Some(*span),
+            Self::UnknownCaptureGroupName(_) => None,
+        }
+    }
+
+    /// Renders the error along with the line of the pattern it occurred in, with a caret pointing
+    /// at the offending character:
+    ///
+    /// ```text
+    /// unexpected input at offset 6 ('X')
+    /// E8 ?? XX
+    ///       ^
+    /// ```
+    pub fn render(&self, input: &str) -> String {
+        let Some(span) = self.span() else {
+            return self.to_string();
+        };
+
+        let offset = span.offset.min(input.len());
+        let line_start = input[..offset].rfind('\n')
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        let line_end = input[offset..].find(['\r', '\n'])
+            .map(|i| offset + i)
+            .unwrap_or(input.len());
+
+        // Columns are counted in characters so the caret lines up with multi-byte characters
+        let column = input[line_start..offset].chars().count();
+
+        format!("{self}\n{}\n{}^", &input[line_start..line_end], " ".repeat(column))
+    }
+}
+
+impl fmt::Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tokenizer(e) => write!(f, "{e}"),
+            Self::CaptureGroupAlreadyOpened(span) => write!(
+                f,
+                "capture group opened at {span} while another capture group is still open",
+            ),
+            Self::CaptureGroupNotOpened(span) => write!(f, "capture group closed at {span} was never opened"),
+            Self::CaptureGroupNotClosed(span) => write!(f, "capture group opened at {span} is never closed"),
+            Self::DuplicateCaptureGroupName(name, span) => write!(
+                f,
+                "capture group name `{name}` at {span} is already in use",
+            ),
+            Self::UnknownCaptureGroupName(name) => write!(f, "no capture group is named `{name}`"),
+            Self::JumpInCaptureGroup(span) => write!(f, "jump at {span} is inside of a capture group"),
+            Self::JumpAtPatternEdge(span) => write!(f, "jump at {span} is at the start or end of the pattern"),
+        }
+    }
+}
+
+impl Error for ParserError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Tokenizer(e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
 pub type ParserResult = Result<Pattern, ParserError>;
@@ -21,9 +106,14 @@ pub(crate) fn parse_pattern(
     let mut bytes = Vec::new();
     let mut mask = Vec::new();
     let mut capture_groups = Vec::<Range<usize>>::new();
-    let mut current_capture_group_start = None as Option<usize>;
+    let mut capture_names = Vec::<Option<String>>::new();
+    let mut current_capture_group_start = None as Option<(usize, Span)>;
+    let mut current_capture_group_name = None as Option<String>;
+    let mut jumps = Vec::<Jump>::new();
+    let mut last_jump_span = None as Option<Span>;
+    let mut alternatives = Vec::<Alternatives>::new();
 
-    for token in tokenizer(input)
+    for (span, token) in tokenizer(input)
         .map_err(ParserError::Tokenizer)?
         .iter() {
 
@@ -32,29 +122,74 @@ pub(crate) fn parse_pattern(
                 bytes.push(*b);
                 mask.push(*m);
             },
+            tokenizer::Token::Alternatives(values) => {
+                // The first value goes into the bytes like any other, the rest is kept aside
+                let (b, m) = values[0];
+                if values.len() > 1 {
+                    alternatives.push(Alternatives { position: bytes.len(), values: values[1..].to_vec() });
+                }
+
+                bytes.push(b);
+                mask.push(m);
+            },
             tokenizer::Token::CaptureGroupOpen => {
                 match current_capture_group_start {
-                    None => current_capture_group_start = Some(bytes.len()),
+                    None => current_capture_group_start = Some((bytes.len(), *span)),
 
                     // Ensure we're not already in a group
-                    Some(_) => return Err(ParserError::CaptureGroupAlreadyOpened),
+                    Some(_) => return Err(ParserError::CaptureGroupAlreadyOpened(*span)),
                 }
             },
+            tokenizer::Token::CaptureGroupName(name) => {
+                // Names have to be unique as otherwise lookups by name would be ambiguous
+                if capture_names.iter().flatten().any(|n| n == name) {
+                    return Err(ParserError::DuplicateCaptureGroupName(name.clone(), *span));
+                }
+
+                current_capture_group_name = Some(name.clone());
+            },
             tokenizer::Token::CaptureGroupClose => {
                 match current_capture_group_start.take() {
-                    Some(start) => capture_groups.push(Range {start, end: bytes.len()}),
+                    Some((start, _)) => {
+                        capture_groups.push(Range {start, end: bytes.len()});
+                        capture_names.push(current_capture_group_name.take());
+                    },
 
                     // Bail if capture group was never opened
-                    None => return Err(ParserError::CaptureGroupNotOpened),
+                    None => return Err(ParserError::CaptureGroupNotOpened(*span)),
+                }
+            },
+            tokenizer::Token::Jump(min, max) => {
+                if current_capture_group_start.is_some() {
+                    return Err(ParserError::JumpInCaptureGroup(*span));
+                }
+
+                if bytes.is_empty() {
+                    return Err(ParserError::JumpAtPatternEdge(*span));
+                }
+
+                match jumps.last_mut() {
+                    // Fold consecutive jumps into a single one
+                    Some(jump) if jump.position == bytes.len() => {
+                        jump.min += min;
+                        jump.max += max;
+                    },
+                    _ => jumps.push(Jump { position: bytes.len(), min: *min, max: *max }),
                 }
+
+                last_jump_span = Some(*span);
             },
         }
     }
 
     // Guard against unclosed capture groups as otherwise it'll silently not
     // capture the input.
-    if current_capture_group_start.is_some() {
-        return Err(ParserError::CaptureGroupNotClosed)
+    if let Some((_, span)) = current_capture_group_start {
+        return Err(ParserError::CaptureGroupNotClosed(span))
+    }
+
+    if jumps.last().is_some_and(|j| j.position == bytes.len()) {
+        return Err(ParserError::JumpAtPatternEdge(last_jump_span.unwrap()));
     }
 
     Ok(Pattern {
@@ -62,6 +197,9 @@ pub(crate) fn parse_pattern(
         bytes,
         mask,
         capture_groups,
+        capture_names,
+        jumps,
+        alternatives,
     })
 }
 
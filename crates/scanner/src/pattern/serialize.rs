@@ -0,0 +1,53 @@
+use std::fmt;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::pattern::Pattern;
+
+/// Patterns are serialized as their byte pattern string so they stay readable in config files.
+impl Serialize for Pattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(PatternVisitor)
+    }
+}
+
+struct PatternVisitor;
+
+impl<'de> de::Visitor<'de> for PatternVisitor {
+    type Value = Pattern;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a byte pattern string")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        Pattern::from_byte_pattern(v).map_err(E::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pattern::Pattern;
+
+    #[test]
+    fn pattern_serializes_as_string() {
+        let pattern = Pattern::from_byte_pattern("48 8b 0d [vtable: ?? ?? ?? ??] {2-4} (e8|e9)").unwrap();
+
+        let json = serde_json::to_string(&pattern).unwrap();
+        assert_eq!(json, r#""48 8B 0D [vtable: ?? ?? ?? ??] {2-4} (E8|E9)""#);
+        assert_eq!(serde_json::from_str::<Pattern>(&json).unwrap(), pattern);
+    }
+
+    #[test]
+    fn pattern_deserialization_reports_parser_errors() {
+        let error = serde_json::from_str::<Pattern>(r#""48 8B ]""#).unwrap_err();
+
+        assert!(error.to_string().contains("offset 6"), "{error}");
+    }
+}
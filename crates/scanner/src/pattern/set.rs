@@ -0,0 +1,270 @@
+use std::collections::VecDeque;
+
+use crate::pattern::Pattern;
+
+/// A collection of named patterns that can be matched in a single pass over the bytes.
+/// For every pattern the longest run of fully-masked bytes is fed into an Aho-Corasick automaton.
+/// Hits in the automaton are candidates that are then verified against the whole pattern.
+#[derive(Debug, Clone)]
+pub struct PatternSet {
+    entries: Vec<(String, Pattern)>,
+    automaton: Automaton,
+    /// Indices of patterns that don't have a single fully-masked byte and have to be checked at
+    /// every offset.
+    unanchored: Vec<usize>,
+}
+
+impl PatternSet {
+    /// Builds the set from a list of named patterns. Later entries replace earlier entries with the
+    /// same name.
+    pub fn new<N: Into<String>>(patterns: impl IntoIterator<Item = (N, Pattern)>) -> Self {
+        let mut entries: Vec<(String, Pattern)> = Vec::new();
+        for (name, pattern) in patterns.into_iter() {
+            let name = name.into();
+            match entries.iter_mut().find(|(n, _)| *n == name) {
+                Some(entry) => entry.1 = pattern,
+                None => entries.push((name, pattern)),
+            }
+        }
+
+        let mut anchors = Vec::new();
+        let mut unanchored = Vec::new();
+        for (index, (_, pattern)) in entries.iter().enumerate() {
+            if pattern.length == 0 {
+                continue;
+            }
+
+            match longest_fixed_run(pattern) {
+                Some(run) => anchors.push(Anchor { pattern: index, offset: run.start, length: run.len() }),
+                None => unanchored.push(index),
+            }
+        }
+
+        let automaton = Automaton::new(&entries, anchors);
+
+        Self { entries, automaton, unanchored }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Retrieves a pattern from the set by its name.
+    pub fn get(&self, name: &str) -> Option<&Pattern> {
+        self.entries.iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, p)| p)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Pattern)> {
+        self.entries.iter().map(|(n, p)| (n.as_str(), p))
+    }
+
+    /// Longest pattern in the set, used to determine how much chunks need to overlap.
+    pub fn max_length(&self) -> usize {
+        self.entries.iter()
+            .map(|(_, p)| p.max_length())
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub(crate) fn entry(&self, index: usize) -> (&str, &Pattern) {
+        let (name, pattern) = &self.entries[index];
+        (name.as_str(), pattern)
+    }
+
+    /// Calls `on_match` with the index of the pattern and the offset for every match in `bytes`.
+    /// Matches of a single pattern are reported in order, matches of different patterns are not
+    /// ordered relative to each other.
+    pub(crate) fn for_each_match(&self, bytes: &[u8], mut on_match: impl FnMut(usize, usize)) {
+        let mut state = 0;
+        for (position, byte) in bytes.iter().enumerate() {
+            state = self.automaton.next_state(state, *byte);
+
+            for anchor in self.automaton.outputs[state].iter() {
+                // Position in the bytes the pattern would have to start at for this anchor hit
+                let Some(start) = (position + 1).checked_sub(anchor.offset + anchor.length) else {
+                    continue;
+                };
+
+                let (_, pattern) = &self.entries[anchor.pattern];
+                if pattern.matches(&bytes[start..]) {
+                    on_match(anchor.pattern, start);
+                }
+            }
+
+            for index in self.unanchored.iter() {
+                // Patterns with jumps are unanchored when there's nothing fixed in front of the jump
+                let pattern = &self.entries[*index].1;
+                let Some(start) = (position + 1).checked_sub(pattern.prefix_length()) else {
+                    continue;
+                };
+
+                if pattern.matches(&bytes[start..]) {
+                    on_match(*index, start);
+                }
+            }
+        }
+    }
+}
+
+/// Location of the fully-masked run of bytes that gets fed into the automaton for a pattern.
+#[derive(Debug, Clone, Copy)]
+struct Anchor {
+    pattern: usize,
+    offset: usize,
+    length: usize,
+}
+
+/// Aho-Corasick automaton compiled down to a DFA so that every byte costs a single lookup.
+#[derive(Debug, Clone)]
+struct Automaton {
+    transitions: Vec<u32>,
+    outputs: Vec<Vec<Anchor>>,
+}
+
+impl Automaton {
+    fn new(entries: &[(String, Pattern)], anchors: Vec<Anchor>) -> Self {
+        // Trie of all the anchors, unset transitions are filled in when computing the failure links
+        let mut transitions = vec![u32::MAX; 256];
+        let mut outputs = vec![Vec::new()];
+
+        for anchor in anchors.into_iter() {
+            let pattern = &entries[anchor.pattern].1;
+
+            let mut state = 0;
+            for byte in pattern.bytes[anchor.offset..anchor.offset + anchor.length].iter() {
+                let slot = state * 256 + *byte as usize;
+                if transitions[slot] == u32::MAX {
+                    transitions[slot] = outputs.len() as u32;
+                    transitions.extend([u32::MAX; 256]);
+                    outputs.push(Vec::new());
+                }
+
+                state = transitions[slot] as usize;
+            }
+
+            outputs[state].push(anchor);
+        }
+
+        // Missing transitions from the root loop back to the root
+        for slot in transitions[0..256].iter_mut() {
+            if *slot == u32::MAX {
+                *slot = 0;
+            }
+        }
+
+        // Walk the trie breadth-first so that the failure target of every state has been completed
+        // by the time we get to it.
+        let mut failure = vec![0usize; outputs.len()];
+        let mut queue = transitions[0..256].iter()
+            .filter(|t| **t != 0)
+            .map(|t| *t as usize)
+            .collect::<VecDeque<usize>>();
+
+        while let Some(state) = queue.pop_front() {
+            let inherited = outputs[failure[state]].clone();
+            outputs[state].extend(inherited);
+
+            for byte in 0..256 {
+                let slot = state * 256 + byte;
+                let fallback = transitions[failure[state] * 256 + byte];
+
+                if transitions[slot] == u32::MAX {
+                    transitions[slot] = fallback;
+                } else {
+                    let child = transitions[slot] as usize;
+                    failure[child] = fallback as usize;
+                    queue.push_back(child);
+                }
+            }
+        }
+
+        Self { transitions, outputs }
+    }
+
+    fn next_state(&self, state: usize, byte: u8) -> usize {
+        self.transitions[state * 256 + byte as usize] as usize
+    }
+}
+
+/// Finds the longest run of fully-masked bytes without alternatives in front of the first jump in
+/// the pattern. Picks the earliest one on ties.
+fn longest_fixed_run(pattern: &Pattern) -> Option<std::ops::Range<usize>> {
+    let mut best: Option<std::ops::Range<usize>> = None;
+
+    // Bytes after a jump aren't at a fixed offset from the start
+    let end = pattern.prefix_length();
+
+    let mut start = 0;
+    for i in 0..=end {
+        if i < end && pattern.mask[i] == 0xFF && pattern.alternatives_at(i).is_empty() {
+            continue;
+        }
+
+        if i > start && best.as_ref().map(|b| b.len() < i - start).unwrap_or(true) {
+            best = Some(start..i);
+        }
+
+        start = i + 1;
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pattern::Pattern;
+    use crate::pattern::set::{longest_fixed_run, PatternSet};
+
+    #[test]
+    fn longest_fixed_run_works() {
+        let pattern = Pattern::from_byte_pattern("?? 01 ?? 02 03 04 m0000???? 05 06").unwrap();
+        assert_eq!(longest_fixed_run(&pattern), Some(3..6));
+
+        let pattern = Pattern::from_byte_pattern("?? ?? m0000????").unwrap();
+        assert_eq!(longest_fixed_run(&pattern), None);
+
+        let pattern = Pattern::from_byte_pattern("01 02 03 (04|05) 06 {1-2} 07 08 09 0A").unwrap();
+        assert_eq!(longest_fixed_run(&pattern), Some(0..3));
+    }
+
+    #[test]
+    fn pattern_set_replaces_duplicate_names() {
+        let set = PatternSet::new([
+            ("a", Pattern::from_byte_slice(&[0x01])),
+            ("b", Pattern::from_byte_slice(&[0x02])),
+            ("a", Pattern::from_byte_slice(&[0x03])),
+        ]);
+
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.get("a").unwrap().bytes, vec![0x03]);
+    }
+
+    #[test]
+    fn pattern_set_finds_anchors_that_are_suffixes_of_each_other() {
+        let set = PatternSet::new([
+            ("long", Pattern::from_byte_pattern("01 02 03 04").unwrap()),
+            ("short", Pattern::from_byte_pattern("?? 03 04").unwrap()),
+            ("unanchored", Pattern::from_byte_pattern("m0000???? ??").unwrap()),
+        ]);
+
+        let mut matches = Vec::new();
+        set.for_each_match(&[0x01, 0x02, 0x03, 0x04], |index, offset| {
+            matches.push((set.entry(index).0.to_string(), offset))
+        });
+        matches.sort();
+
+        assert_eq!(matches, vec![
+            ("long".to_string(), 0),
+            ("short".to_string(), 1),
+            ("unanchored".to_string(), 0),
+            ("unanchored".to_string(), 1),
+            ("unanchored".to_string(), 2),
+        ]);
+    }
+}
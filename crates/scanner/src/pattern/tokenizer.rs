@@ -1,40 +1,161 @@
+use std::fmt;
+use std::error::Error;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
 #[derive(Debug, PartialEq)]
 pub(crate) enum Token {
     CaptureGroupOpen,
+    /// Name of the capture group that was opened by the preceding `CaptureGroupOpen`.
+    CaptureGroupName(String),
     CaptureGroupClose,
     ByteValue(u8, u8),
+    /// Skips anywhere between the first and the second amount of bytes.
+    Jump(usize, usize),
+    /// A single byte that can take any of the values, as byte and mask pairs.
+    Alternatives(Vec<(u8, u8)>),
+}
+
+/// Location of a character in the pattern string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    /// Offset in bytes from the start of the pattern string.
+    pub offset: usize,
+    /// The character at the offset, `None` if the offset is at the end of the pattern.
+    pub character: Option<char>,
+}
+
+impl Span {
+    /// The span of the character at `offset` in `input`.
+    pub(crate) fn at(input: &str, offset: usize) -> Self {
+        Self { offset, character: input[offset..].chars().next() }
+    }
 }
 
-#[derive(Debug)]
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.character {
+            Some(c) => write!(f, "offset {} ({c:?})", self.offset),
+            None => write!(f, "offset {} (end of pattern)", self.offset),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
 pub enum TokenizationError {
-    UnknownInput,
-    IncompleteByte,
+    UnknownInput(Span),
+    /// A byte was cut short, the span points at the start of the byte.
+    IncompleteByte(Span),
+    /// A jump wasn't written as `{min-max}` or `{length}`, or its minimum exceeds its maximum.
+    InvalidJump(Span),
+    /// An alternation wasn't written as bytes separated by `|` in between parentheses.
+    InvalidAlternation(Span),
+    /// A string literal wasn't closed, in which case the span points at its start, or contains an
+    /// unknown escape.
+    InvalidString(Span),
+}
+
+impl TokenizationError {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::UnknownInput(span)
+            | Self::IncompleteByte(span)
+            | Self::InvalidJump(span)
+            | Self::InvalidAlternation(span)
+            | Self::InvalidString(span) => *span,
+        }
+    }
+}
+
+impl fmt::Display for TokenizationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownInput(span) => write!(f, "unexpected input at {span}"),
+            Self::IncompleteByte(span) => write!(f, "incomplete byte at {span}"),
+            Self::InvalidJump(span) => write!(
+                f,
+                "invalid jump at {span}, expected `{{min-max}}` or `{{length}}` with min <= max",
+            ),
+            Self::InvalidAlternation(span) => write!(
+                f,
+                "invalid alternation at {span}, expected bytes separated by `|` in between parentheses",
+            ),
+            Self::InvalidString(span) => write!(
+                f,
+                "invalid string at {span}, expected a closing `\"` and escapes like `\\\"` or `\\n`",
+            ),
+        }
+    }
 }
 
-pub(crate) type TokenizerFn = fn(input: &str) -> Result<Vec<Token>, TokenizationError>;
+impl Error for TokenizationError {}
 
-pub(crate) fn tokenize_bit_pattern(input: &str) -> Result<Vec<Token>, TokenizationError> {
-    let mut input_iter = input.chars().peekable();
+pub(crate) type TokenizerFn = fn(input: &str) -> Result<Vec<(Span, Token)>, TokenizationError>;
+
+/// Walks over the characters of the pattern string while keeping track of where it's at.
+#[derive(Clone)]
+struct Cursor<'a> {
+    input: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(input: &'a str) -> Self {
+        Self { input, chars: input.char_indices().peekable() }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        self.chars.next().map(|(_, c)| c)
+    }
+
+    fn next_if(&mut self, predicate: impl FnOnce(&char) -> bool) -> Option<char> {
+        self.chars.next_if(|(_, c)| predicate(c)).map(|(_, c)| c)
+    }
+
+    /// Span of the character that will be read next.
+    fn span(&mut self) -> Span {
+        match self.chars.peek() {
+            Some((offset, character)) => Span { offset: *offset, character: Some(*character) },
+            None => Span { offset: self.input.len(), character: None },
+        }
+    }
+}
+
+pub(crate) fn tokenize_bit_pattern(input: &str) -> Result<Vec<(Span, Token)>, TokenizationError> {
+    let mut cursor = Cursor::new(input);
 
     let mut tokens = Vec::new();
-    while let Some(current_character) = input_iter.next() {
+    loop {
+        let span = cursor.span();
+        let Some(current_character) = cursor.next() else {
+            break;
+        };
+
         match current_character {
             ' ' | '\n' | '\r' => { },
-            '[' => tokens.push(Token::CaptureGroupOpen),
-            ']' => tokens.push(Token::CaptureGroupClose),
-            _ => {
-                let c = current_character;
+            '[' => {
+                tokens.push((span, Token::CaptureGroupOpen));
+                if let Some(name) = take_capture_group_name(&mut cursor) {
+                    tokens.push(name);
+                }
+            },
+            ']' => tokens.push((span, Token::CaptureGroupClose)),
+            '{' => tokens.push((span, take_jump(span, &mut cursor)?)),
+            c => {
+                if !is_bit_char(&c) {
+                    return Err(TokenizationError::UnknownInput(span));
+                }
 
                 let mut result_byte = ((c == '1') as u8) << 7;
                 let mut result_mask = ((c != '?' && c != '.') as u8) << 7;
 
                 for i in 0..7 {
-                    match input_iter.next() {
-                        None => return Err(TokenizationError::IncompleteByte),
+                    let character_span = cursor.span();
+                    match cursor.next() {
+                        None => return Err(TokenizationError::IncompleteByte(span)),
                         Some(c) => {
-                            println!("Character \"{c}\"");
                             if !is_bit_char(&c) {
-                                return Err(TokenizationError::UnknownInput);
+                                return Err(TokenizationError::UnknownInput(character_span));
                             }
 
                             let shift = 6 - i;
@@ -44,7 +165,7 @@ pub(crate) fn tokenize_bit_pattern(input: &str) -> Result<Vec<Token>, Tokenizati
                     }
                 }
 
-                tokens.push(Token::ByteValue(result_byte, result_mask))
+                tokens.push((span, Token::ByteValue(result_byte, result_mask)))
             },
         };
     }
@@ -52,80 +173,251 @@ pub(crate) fn tokenize_bit_pattern(input: &str) -> Result<Vec<Token>, Tokenizati
     Ok(tokens)
 }
 
-pub(crate) fn tokenize_byte_pattern(input: &str) -> Result<Vec<Token>, TokenizationError> {
-    // Can probably just shift the ASCII values as a speed up
-    let input_lower = input.to_lowercase();
-    let mut input_iter = input_lower.chars().peekable();
+pub(crate) fn tokenize_byte_pattern(input: &str) -> Result<Vec<(Span, Token)>, TokenizationError> {
+    // Characters are lowercased one by one as capture group names need to keep their casing
+    let mut cursor = Cursor::new(input);
 
     let mut tokens = Vec::new();
-    while let Some(current_character) = input_iter.next() {
-        match current_character {
+    loop {
+        let span = cursor.span();
+        let Some(current_character) = cursor.next() else {
+            break;
+        };
+
+        match current_character.to_ascii_lowercase() {
             ' ' | '\n' | '\r' => { },
-            '[' => tokens.push(Token::CaptureGroupOpen),
-            ']' => tokens.push(Token::CaptureGroupClose),
-            '?' => {
-                // Collapse double question marks
-                if input_iter.peek() == Some(&'?') {
-                    input_iter.next();
+            '[' => {
+                tokens.push((span, Token::CaptureGroupOpen));
+                if let Some(name) = take_capture_group_name(&mut cursor) {
+                    tokens.push(name);
                 }
-
-                tokens.push(Token::ByteValue(0x00, 0x00))
-            }
+            },
+            ']' => tokens.push((span, Token::CaptureGroupClose)),
+            '{' => tokens.push((span, take_jump(span, &mut cursor)?)),
+            '(' => tokens.push((span, take_alternatives(&mut cursor)?)),
+            '"' | 'u' | 'i' => tokens.extend(take_string(current_character, span, &mut cursor)?),
             'm' => {
-                let mut result_byte = 0u8;
-                let mut result_mask = 0u8;
+                let (byte, mask) = take_bit_byte(span, &mut cursor)?;
+                tokens.push((span, Token::ByteValue(byte, mask)))
+            },
+            current_character => {
+                let (byte, mask) = take_hex_byte(current_character, span, &mut cursor)?;
+                tokens.push((span, Token::ByteValue(byte, mask)))
+            }
+        };
+    }
 
-                // Consume next 8 characters
-                for i in 0..8 {
-                    match input_iter.next() {
-                        None => return Err(TokenizationError::IncompleteByte),
-                        Some(c) => {
-                            if !is_bit_char(&c) {
-                                return Err(TokenizationError::UnknownInput);
-                            }
+    Ok(tokens)
+}
 
-                            let shift = 7 - i;
-                            result_byte |= ((c == '1') as u8) << shift;
-                            result_mask |= ((c != '?' && c != '.') as u8) << shift;
-                        }
-                    }
+/// Reads a byte written as two hex characters, where either character can be a `?` to leave that
+/// nibble unmatched. A lone `?` or `??` matches the entire byte. `first` is the character that has
+/// already been read at `span`.
+fn take_hex_byte(first: char, span: Span, cursor: &mut Cursor) -> Result<(u8, u8), TokenizationError> {
+    let (high, high_mask) = parse_nibble(first)
+        .ok_or(TokenizationError::UnknownInput(span))?;
+
+    let low = cursor.next_if(|c| parse_nibble(*c).is_some())
+        .and_then(parse_nibble);
+
+    let (low, low_mask) = match low {
+        Some(low) => low,
+        None if first == '?' => (0x0, 0x0),
+        None => return Err(TokenizationError::IncompleteByte(span)),
+    };
+
+    Ok((high << 4 | low, high_mask << 4 | low_mask))
+}
+
+/// Reads the 8 bit characters of a byte written as `m0101????`, after the `m` at `span`.
+fn take_bit_byte(span: Span, cursor: &mut Cursor) -> Result<(u8, u8), TokenizationError> {
+    let mut result_byte = 0u8;
+    let mut result_mask = 0u8;
+
+    // Consume next 8 characters
+    for i in 0..8 {
+        let character_span = cursor.span();
+        match cursor.next() {
+            None => return Err(TokenizationError::IncompleteByte(span)),
+            Some(c) => {
+                if !is_bit_char(&c) {
+                    return Err(TokenizationError::UnknownInput(character_span));
                 }
 
-                tokens.push(Token::ByteValue(result_byte, result_mask))
+                let shift = 7 - i;
+                result_byte |= ((c == '1') as u8) << shift;
+                result_mask |= ((c != '?' && c != '.') as u8) << shift;
+            }
+        }
+    }
+
+    Ok((result_byte, result_mask))
+}
+
+/// Parses a single hex character to its value and mask.
+fn parse_nibble(input: char) -> Option<(u8, u8)> {
+    match input {
+        '?' => Some((0x0, 0x0)),
+        c => c.to_digit(16).map(|v| (v as u8, 0xF)),
+    }
+}
+
+/// Consumes the rest of an `(E8|E9)` alternation after the opening parenthesis.
+fn take_alternatives(cursor: &mut Cursor) -> Result<Token, TokenizationError> {
+    let mut values = Vec::new();
+
+    loop {
+        skip_whitespace(cursor);
+        let span = cursor.span();
+        let value = match cursor.next_if(|c| *c == 'm' || *c == 'M' || parse_nibble(*c).is_some()) {
+            Some('m' | 'M') => take_bit_byte(span, cursor)?,
+            Some(first) => take_hex_byte(first, span, cursor)?,
+            None => return Err(TokenizationError::InvalidAlternation(span)),
+        };
+        values.push(value);
+
+        skip_whitespace(cursor);
+        let span = cursor.span();
+        match cursor.next() {
+            Some('|') => continue,
+            Some(')') => return Ok(Token::Alternatives(values)),
+            _ => return Err(TokenizationError::InvalidAlternation(span)),
+        }
+    }
+}
+
+/// Consumes a string literal like `"abc"`, prefixed by `u` for UTF-16LE and by `i` to ignore the
+/// case of ASCII letters. `first` is the character that has already been read at `span`, which is
+/// either the opening quote or a prefix. Other strings are encoded as UTF-8.
+fn take_string(first: char, span: Span, cursor: &mut Cursor) -> Result<Vec<(Span, Token)>, TokenizationError> {
+    let mut utf16 = false;
+    let mut case_insensitive = false;
+
+    let mut current = first;
+    loop {
+        match current.to_ascii_lowercase() {
+            '"' => break,
+            'u' if !utf16 => utf16 = true,
+            'i' if !case_insensitive => case_insensitive = true,
+            _ => return Err(TokenizationError::UnknownInput(span)),
+        }
+
+        current = cursor.next()
+            .ok_or(TokenizationError::UnknownInput(span))?;
+    }
+
+    // Case is ignored by leaving the bit that tells upper and lower case letters apart unmatched
+    let byte = |value: u8| match case_insensitive && value.is_ascii_alphabetic() {
+        true => Token::ByteValue(value & 0xDF, 0xDF),
+        false => Token::ByteValue(value, 0xFF),
+    };
+
+    let mut tokens = Vec::new();
+    loop {
+        let character_span = cursor.span();
+        let character = match cursor.next() {
+            None => return Err(TokenizationError::InvalidString(span)),
+            Some('"') => return Ok(tokens),
+            Some('\\') => match cursor.next() {
+                Some('"') => '"',
+                Some('\\') => '\\',
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some('t') => '\t',
+                Some('0') => '\0',
+                _ => return Err(TokenizationError::InvalidString(character_span)),
             },
-            _ => {
-                // Ensure current character is a 0-9a-f.
-                if !is_radix_16_char(&current_character) {
-                    return Err(TokenizationError::UnknownInput);
-                }
+            Some(character) => character,
+        };
 
-                let next_character = input_iter.next();
-                // Ensure next character is available and it's 0-9a-f.
-                if next_character.is_none() || !is_radix_16_char(next_character.as_ref().unwrap()) {
-                    return Err(TokenizationError::IncompleteByte);
+        match utf16 {
+            true => {
+                for unit in character.encode_utf16(&mut [0; 2]) {
+                    let [low, high] = unit.to_le_bytes();
+                    tokens.push((character_span, byte(low)));
+                    tokens.push((character_span, Token::ByteValue(high, 0xFF)));
+                }
+            },
+            false => {
+                for value in character.encode_utf8(&mut [0; 4]).bytes() {
+                    tokens.push((character_span, byte(value)));
                 }
+            },
+        }
+    }
+}
 
-                // Parse both characters together as a single byte
-                // TODO: can probably parse both independently and shift them into place?
-                let mut byte_string = String::new();
-                byte_string.push(current_character);
-                byte_string.push(next_character.unwrap());
-                let parsed_byte = u8::from_str_radix(byte_string.as_str(), 16).unwrap();
+/// Consumes the `name:` that can follow the opening of a capture group. Leaves the input
+/// untouched if there's no name, as `[ab cd]` should still be read as bytes.
+fn take_capture_group_name(cursor: &mut Cursor) -> Option<(Span, Token)> {
+    let mut lookahead = cursor.clone();
+    skip_whitespace(&mut lookahead);
 
-                tokens.push(Token::ByteValue(parsed_byte, 0xFF))
-            }
-        };
+    let span = lookahead.span();
+    let mut name = String::new();
+    while let Some(c) = lookahead.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+        name.push(c);
     }
 
-    Ok(tokens)
+    skip_whitespace(&mut lookahead);
+    if !is_capture_group_name(&name) || lookahead.next() != Some(':') {
+        return None;
+    }
+
+    *cursor = lookahead;
+    Some((span, Token::CaptureGroupName(name)))
 }
 
-const RADIX_16_CHARS: [char; 16] = [
-    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'a', 'b', 'c', 'd', 'e', 'f'
-];
+/// Consumes the rest of a `{min-max}` or `{length}` jump after the opening brace at `span`.
+fn take_jump(span: Span, cursor: &mut Cursor) -> Result<Token, TokenizationError> {
+    skip_whitespace(cursor);
+    let min = take_number(cursor)?;
+    skip_whitespace(cursor);
+
+    let separator_span = cursor.span();
+    let max = match cursor.next() {
+        Some('}') => return Ok(Token::Jump(min, min)),
+        Some('-') => {
+            skip_whitespace(cursor);
+            take_number(cursor)?
+        },
+        _ => return Err(TokenizationError::InvalidJump(separator_span)),
+    };
+
+    skip_whitespace(cursor);
+    let close_span = cursor.span();
+    if cursor.next() != Some('}') {
+        return Err(TokenizationError::InvalidJump(close_span));
+    }
 
-fn is_radix_16_char(input: &char) -> bool {
-    RADIX_16_CHARS.contains(input)
+    if min > max {
+        return Err(TokenizationError::InvalidJump(span));
+    }
+
+    Ok(Token::Jump(min, max))
+}
+
+fn take_number(cursor: &mut Cursor) -> Result<usize, TokenizationError> {
+    let span = cursor.span();
+    let mut number = String::new();
+    while let Some(c) = cursor.next_if(|c| c.is_ascii_digit()) {
+        number.push(c);
+    }
+
+    number.parse::<usize>()
+        .map_err(|_| TokenizationError::InvalidJump(span))
+}
+
+fn skip_whitespace(cursor: &mut Cursor) {
+    while cursor.next_if(|c| c.is_whitespace()).is_some() { }
+}
+
+/// Names have to start with a letter or underscore, followed by letters, digits or underscores.
+pub(crate) fn is_capture_group_name(input: &str) -> bool {
+    let mut chars = input.chars();
+
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
 }
 
 const BIT_CHARS: [char; 4] = [
@@ -139,11 +431,18 @@ fn is_bit_char(input: &char) -> bool {
 #[cfg(test)]
 #[allow(clippy::bool_assert_comparison)]
 mod tests {
-    use crate::pattern::tokenizer::{Token, tokenize_byte_pattern};
+    use crate::pattern::tokenizer::{Span, Token, TokenizationError, tokenize_byte_pattern, tokenize_bit_pattern};
+
+    fn tokens(input: &str) -> impl Iterator<Item = Token> {
+        tokenize_byte_pattern(input)
+            .unwrap()
+            .into_iter()
+            .map(|(_, token)| token)
+    }
 
     #[test]
     fn tokenize_works() {
-        let mut tokens = tokenize_byte_pattern("00 [11 ?? ??] m10101?1? EF").unwrap().into_iter();
+        let mut tokens = tokens("00 [11 ?? ??] m10101?1? EF");
 
         assert_eq!(tokens.next(), Some(Token::ByteValue(0x00, 0xFF)));
         assert_eq!(tokens.next(), Some(Token::CaptureGroupOpen));
@@ -155,4 +454,169 @@ mod tests {
         assert_eq!(tokens.next(), Some(Token::ByteValue(0xEF, 0xFF)));
         assert_eq!(tokens.next(), None);
     }
+
+    #[test]
+    fn tokenize_reads_capture_group_names() {
+        let mut tokens = tokens("[vTable_1: AB] [ab cd] [ _x : ??]");
+
+        assert_eq!(tokens.next(), Some(Token::CaptureGroupOpen));
+        assert_eq!(tokens.next(), Some(Token::CaptureGroupName("vTable_1".to_string())));
+        assert_eq!(tokens.next(), Some(Token::ByteValue(0xAB, 0xFF)));
+        assert_eq!(tokens.next(), Some(Token::CaptureGroupClose));
+        assert_eq!(tokens.next(), Some(Token::CaptureGroupOpen));
+        assert_eq!(tokens.next(), Some(Token::ByteValue(0xAB, 0xFF)));
+        assert_eq!(tokens.next(), Some(Token::ByteValue(0xCD, 0xFF)));
+        assert_eq!(tokens.next(), Some(Token::CaptureGroupClose));
+        assert_eq!(tokens.next(), Some(Token::CaptureGroupOpen));
+        assert_eq!(tokens.next(), Some(Token::CaptureGroupName("_x".to_string())));
+        assert_eq!(tokens.next(), Some(Token::ByteValue(0x00, 0x00)));
+        assert_eq!(tokens.next(), Some(Token::CaptureGroupClose));
+        assert_eq!(tokens.next(), None);
+    }
+
+    #[test]
+    fn tokenize_reads_jumps() {
+        let mut tokens = tokens("E8 {4-16} C3 { 2 } ??");
+
+        assert_eq!(tokens.next(), Some(Token::ByteValue(0xE8, 0xFF)));
+        assert_eq!(tokens.next(), Some(Token::Jump(4, 16)));
+        assert_eq!(tokens.next(), Some(Token::ByteValue(0xC3, 0xFF)));
+        assert_eq!(tokens.next(), Some(Token::Jump(2, 2)));
+        assert_eq!(tokens.next(), Some(Token::ByteValue(0x00, 0x00)));
+        assert_eq!(tokens.next(), None);
+    }
+
+    #[test]
+    fn tokenize_reads_nibble_wildcards() {
+        let mut tokens = tokens("4? ?F ?? ? a?");
+
+        assert_eq!(tokens.next(), Some(Token::ByteValue(0x40, 0xF0)));
+        assert_eq!(tokens.next(), Some(Token::ByteValue(0x0F, 0x0F)));
+        assert_eq!(tokens.next(), Some(Token::ByteValue(0x00, 0x00)));
+        assert_eq!(tokens.next(), Some(Token::ByteValue(0x00, 0x00)));
+        assert_eq!(tokens.next(), Some(Token::ByteValue(0xA0, 0xF0)));
+        assert_eq!(tokens.next(), None);
+    }
+
+    #[test]
+    fn tokenize_reads_alternatives() {
+        let mut tokens = tokens("(E8|e9) ( 48 | 4? | m0?00?000 ) (90)");
+
+        assert_eq!(tokens.next(), Some(Token::Alternatives(vec![(0xE8, 0xFF), (0xE9, 0xFF)])));
+        assert_eq!(tokens.next(), Some(Token::Alternatives(vec![(0x48, 0xFF), (0x40, 0xF0), (0x00, 0xB7)])));
+        assert_eq!(tokens.next(), Some(Token::Alternatives(vec![(0x90, 0xFF)])));
+        assert_eq!(tokens.next(), None);
+    }
+
+    #[test]
+    fn tokenize_reads_strings() {
+        let bytes = |input| tokens(input).collect::<Vec<_>>();
+
+        assert_eq!(bytes("\"Ab\" 00"), vec![
+            Token::ByteValue(b'A', 0xFF),
+            Token::ByteValue(b'b', 0xFF),
+            Token::ByteValue(0x00, 0xFF),
+        ]);
+        assert_eq!(bytes("u\"A\u{e9}\""), vec![
+            Token::ByteValue(b'A', 0xFF),
+            Token::ByteValue(0x00, 0xFF),
+            Token::ByteValue(0xE9, 0xFF),
+            Token::ByteValue(0x00, 0xFF),
+        ]);
+        assert_eq!(bytes("i\"a_Z\""), vec![
+            Token::ByteValue(b'A', 0xDF),
+            Token::ByteValue(b'_', 0xFF),
+            Token::ByteValue(b'Z', 0xDF),
+        ]);
+        assert_eq!(bytes("Ui\"b\"")[0], Token::ByteValue(b'B', 0xDF));
+        assert_eq!(bytes("\"\\\"\\\\\\n\\0\""), vec![
+            Token::ByteValue(b'"', 0xFF),
+            Token::ByteValue(b'\\', 0xFF),
+            Token::ByteValue(b'\n', 0xFF),
+            Token::ByteValue(0x00, 0xFF),
+        ]);
+        assert_eq!(bytes("[name: \"\"] \"\u{e9}\"").len(), 5);
+    }
+
+    #[test]
+    fn tokenize_rejects_invalid_strings() {
+        assert_eq!(
+            tokenize_byte_pattern("E8 \"abc").unwrap_err(),
+            TokenizationError::InvalidString(Span { offset: 3, character: Some('"') }),
+        );
+        assert_eq!(
+            tokenize_byte_pattern("i\"a\\q\"").unwrap_err(),
+            TokenizationError::InvalidString(Span { offset: 3, character: Some('\\') }),
+        );
+        assert_eq!(
+            tokenize_byte_pattern("E8 uu\"a\"").unwrap_err(),
+            TokenizationError::UnknownInput(Span { offset: 3, character: Some('u') }),
+        );
+        assert!(tokenize_byte_pattern("E8 u").is_err());
+        assert!(tokenize_bit_pattern("\"a\"").is_err());
+    }
+
+    #[test]
+    fn tokenize_rejects_invalid_alternatives() {
+        assert!(tokenize_byte_pattern("()").is_err());
+        assert!(tokenize_byte_pattern("(E8|)").is_err());
+        assert!(tokenize_byte_pattern("(E8 E9)").is_err());
+        assert!(tokenize_byte_pattern("(E8|E9").is_err());
+        assert!(tokenize_byte_pattern("(E|E9)").is_err());
+    }
+
+    #[test]
+    fn tokenize_rejects_invalid_jumps() {
+        assert!(tokenize_byte_pattern("E8 {16-4} C3").is_err());
+        assert!(tokenize_byte_pattern("E8 {-4} C3").is_err());
+        assert!(tokenize_byte_pattern("E8 {4-} C3").is_err());
+        assert!(tokenize_byte_pattern("E8 {4-16 C3").is_err());
+    }
+
+    #[test]
+    fn tokenize_rejects_invalid_capture_group_names() {
+        assert!(tokenize_byte_pattern("[1x: ??]").is_err());
+        assert!(tokenize_byte_pattern("[: ??]").is_err());
+        assert!(tokenize_byte_pattern("AB x: ??").is_err());
+    }
+
+    #[test]
+    fn tokenize_reports_where_it_went_wrong() {
+        assert_eq!(
+            tokenize_byte_pattern("E8 ?? XX").unwrap_err(),
+            TokenizationError::UnknownInput(Span { offset: 6, character: Some('X') }),
+        );
+        assert_eq!(
+            tokenize_byte_pattern("E8 9 C3").unwrap_err(),
+            TokenizationError::IncompleteByte(Span { offset: 3, character: Some('9') }),
+        );
+        assert_eq!(
+            tokenize_byte_pattern("E8 m0101").unwrap_err(),
+            TokenizationError::IncompleteByte(Span { offset: 3, character: Some('m') }),
+        );
+        assert_eq!(
+            tokenize_byte_pattern("E8 {4-16").unwrap_err(),
+            TokenizationError::InvalidJump(Span { offset: 8, character: None }),
+        );
+        assert_eq!(
+            tokenize_byte_pattern("E8 {16-4}").unwrap_err(),
+            TokenizationError::InvalidJump(Span { offset: 3, character: Some('{') }),
+        );
+        assert_eq!(
+            tokenize_byte_pattern("(E8|E9) \u{e9}").unwrap_err(),
+            TokenizationError::UnknownInput(Span { offset: 8, character: Some('\u{e9}') }),
+        );
+        assert_eq!(
+            tokenize_byte_pattern("(E8 E9)").unwrap_err(),
+            TokenizationError::InvalidAlternation(Span { offset: 4, character: Some('E') }),
+        );
+        assert_eq!(
+            tokenize_bit_pattern("00000000 0000200").unwrap_err(),
+            TokenizationError::UnknownInput(Span { offset: 13, character: Some('2') }),
+        );
+        assert_eq!(
+            tokenize_bit_pattern("00000000 x0000000").unwrap_err(),
+            TokenizationError::UnknownInput(Span { offset: 9, character: Some('x') }),
+        );
+    }
 }
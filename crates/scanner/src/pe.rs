@@ -0,0 +1,195 @@
+/// Index of the exception directory in the data directories.
+pub(crate) const EXCEPTION_DIRECTORY: usize = 3;
+
+/// Section characteristics flag for sections with executable code.
+#[cfg(feature = "module")]
+pub(crate) const IMAGE_SCN_MEM_EXECUTE: u32 = 0x20000000;
+/// Section characteristics flag for sections that can be read from.
+#[cfg(feature = "module")]
+pub(crate) const IMAGE_SCN_MEM_READ: u32 = 0x40000000;
+
+pub(crate) fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(offset..offset + 2)?.try_into().unwrap()))
+}
+
+pub(crate) fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(offset..offset + 4)?.try_into().unwrap()))
+}
+
+/// Offset of the NT headers, `None` if `image` doesn't start with PE headers. Works on both mapped
+/// modules and files on disk as the headers are the same for both.
+pub(crate) fn nt_headers(image: &[u8]) -> Option<usize> {
+    if image.get(0..2)? != b"MZ" {
+        return None;
+    }
+
+    let nt_headers = read_u32(image, 0x3C)? as usize;
+    if image.get(nt_headers..nt_headers + 4)? != b"PE\0\0" {
+        return None;
+    }
+
+    Some(nt_headers)
+}
+
+/// The RVA and size of a data directory, `None` if it's missing or empty.
+pub(crate) fn data_directory(image: &[u8], index: usize) -> Option<(usize, usize)> {
+    let optional_header = nt_headers(image)? + 24;
+
+    // The data directories move depending on whether it's a PE32 or PE32+ image
+    let (count, directories) = match read_u16(image, optional_header)? {
+        0x10B => (read_u32(image, optional_header + 92)?, optional_header + 96),
+        0x20B => (read_u32(image, optional_header + 108)?, optional_header + 112),
+        _ => return None,
+    };
+
+    if index >= count as usize {
+        return None;
+    }
+
+    let rva = read_u32(image, directories + index * 8)? as usize;
+    let size = read_u32(image, directories + index * 8 + 4)? as usize;
+    (rva != 0 && size != 0).then_some((rva, size))
+}
+
+/// The base address of a module loaded in the current process along with its PE headers. The
+/// slice is only valid for as long as the module stays loaded.
+#[cfg(all(windows, feature = "cache"))]
+pub(crate) fn loaded_module_headers(module: &str) -> Option<(usize, &'static [u8])> {
+    let base = broadsword_microsoft_runtime::module::get_module_handle(module).ok()?;
+
+    // SAFETY: the headers are in the first page of the module, which is mapped while it's loaded
+    let headers = unsafe { std::slice::from_raw_parts(base as *const u8, 0x1000) };
+    Some((base, headers))
+}
+
+/// Images and code for tests, laid out like a 64-bit executable loaded at its preferred base. Most
+/// of the users are behind features, so parts go unused depending on what's enabled.
+#[cfg(test)]
+#[allow(dead_code)]
+pub(crate) mod fixtures {
+    /// Preferred base of 64-bit executables.
+    pub(crate) const IMAGE_BASE: usize = 0x140000000;
+    /// Address of `.text`, the first section.
+    pub(crate) const TEXT_BASE: usize = IMAGE_BASE + 0x1000;
+    /// Address of `.data`, a few pages after the code.
+    pub(crate) const DATA_BASE: usize = IMAGE_BASE + 0x5000;
+
+    /// Offset of the optional header, the NT headers go at 0x80.
+    const OPTIONAL_HEADER: usize = 0x98;
+    /// Offset of the section table, right after a PE32+ optional header with 16 data directories.
+    const SECTION_TABLE: usize = OPTIONAL_HEADER + 0xF0;
+
+    /// A PE32+ image with 16 empty data directories and no sections. Only the fields the parsers
+    /// read are filled in.
+    pub(crate) struct TestImage {
+        bytes: Vec<u8>,
+        sections: usize,
+    }
+
+    impl TestImage {
+        /// An image of `size` bytes, `SizeOfImage` matches it until told otherwise.
+        pub(crate) fn new(size: usize) -> Self {
+            let mut bytes = vec![0u8; size];
+            bytes[0..2].copy_from_slice(b"MZ");
+            bytes[0x3C..0x40].copy_from_slice(&0x80u32.to_le_bytes());
+            bytes[0x80..0x84].copy_from_slice(b"PE\0\0");
+            bytes[0x94..0x96].copy_from_slice(&0xF0u16.to_le_bytes());
+            bytes[OPTIONAL_HEADER..OPTIONAL_HEADER + 2].copy_from_slice(&0x20Bu16.to_le_bytes());
+            bytes[OPTIONAL_HEADER + 108..OPTIONAL_HEADER + 112].copy_from_slice(&16u32.to_le_bytes());
+
+            Self { bytes, sections: 0 }.size_of_image(size as u32)
+        }
+
+        pub(crate) fn timestamp(self, timestamp: u32) -> Self {
+            self.write(0x88, &timestamp.to_le_bytes())
+        }
+
+        pub(crate) fn size_of_image(self, size: u32) -> Self {
+            self.write(OPTIONAL_HEADER + 56, &size.to_le_bytes())
+        }
+
+        /// Appends a section header, `name` is padded with zeroes to 8 bytes.
+        pub(crate) fn section(mut self, name: &str, rva: u32, virtual_size: u32) -> Self {
+            let header = SECTION_TABLE + self.sections * 40;
+            let mut padded = [0u8; 8];
+            padded[..name.len()].copy_from_slice(name.as_bytes());
+
+            self.sections += 1;
+            let count = self.sections as u16;
+            self.write(0x86, &count.to_le_bytes())
+                .write(header, &padded)
+                .write(header + 8, &virtual_size.to_le_bytes())
+                .write(header + 12, &rva.to_le_bytes())
+        }
+
+        pub(crate) fn data_directory(self, index: usize, rva: u32, size: u32) -> Self {
+            let directory = OPTIONAL_HEADER + 112 + index * 8;
+            self.write(directory, &rva.to_le_bytes())
+                .write(directory + 4, &size.to_le_bytes())
+        }
+
+        /// Copies `bytes` into the image at `offset`.
+        pub(crate) fn write(mut self, offset: usize, bytes: &[u8]) -> Self {
+            self.bytes[offset..offset + bytes.len()].copy_from_slice(bytes);
+            self
+        }
+
+        pub(crate) fn into_bytes(self) -> Vec<u8> {
+            self.bytes
+        }
+    }
+
+    /// Writes x86-64 code that's placed at `base`, so relative operands can be given by the address
+    /// they refer to. Gaps are padded with `int3`.
+    pub(crate) struct Code {
+        base: usize,
+        bytes: Vec<u8>,
+    }
+
+    impl Code {
+        pub(crate) fn new(base: usize) -> Self {
+            Self { base, bytes: Vec::new() }
+        }
+
+        /// Address of the next byte that's written.
+        pub(crate) fn address(&self) -> usize {
+            self.base + self.bytes.len()
+        }
+
+        /// Pads up to `offset` from the base.
+        pub(crate) fn at(mut self, offset: usize) -> Self {
+            assert!(offset >= self.bytes.len(), "code at {offset:#x} overlaps what's already written");
+            self.bytes.resize(offset, 0xCC);
+            self
+        }
+
+        pub(crate) fn bytes(mut self, bytes: &[u8]) -> Self {
+            self.bytes.extend_from_slice(bytes);
+            self
+        }
+
+        /// An instruction with a RIP-relative operand that refers to `target`. `opcode` is everything
+        /// in front of the displacement and `immediate` everything after it.
+        pub(crate) fn rip_relative(self, opcode: &[u8], target: usize, immediate: &[u8]) -> Self {
+            let end = self.address() + opcode.len() + 4 + immediate.len();
+            let displacement = (target as isize - end as isize) as i32;
+            self.bytes(opcode)
+                .bytes(&displacement.to_le_bytes())
+                .bytes(immediate)
+        }
+
+        /// `call rel32`
+        pub(crate) fn call(self, target: usize) -> Self {
+            self.rip_relative(&[0xE8], target, &[])
+        }
+
+        /// `jmp rel32`
+        pub(crate) fn jmp(self, target: usize) -> Self {
+            self.rip_relative(&[0xE9], target, &[])
+        }
+
+        pub(crate) fn into_bytes(self) -> Vec<u8> {
+            self.bytes
+        }
+    }
+}
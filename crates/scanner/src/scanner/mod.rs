@@ -1,7 +1,13 @@
+use std::fmt;
+use std::error::Error;
+
 use crate::scanner::result::ScanResultCapture;
 
+pub mod pool;
 pub mod result;
+pub mod simd;
 pub mod simple;
+pub mod streaming;
 pub mod threaded;
 
 #[derive(Debug, PartialEq)]
@@ -9,3 +15,144 @@ pub struct ScanResult {
     pub location: usize,
     pub captures: Vec<ScanResultCapture>,
 }
+
+impl ScanResult {
+    /// Retrieves a capture by the index of its capture group in the pattern.
+    pub fn capture(&self, index: usize) -> Option<&ScanResultCapture> {
+        self.captures.get(index)
+    }
+
+    /// Retrieves a capture by the name of its capture group in the pattern.
+    pub fn named(&self, name: &str) -> Option<&ScanResultCapture> {
+        self.captures.iter()
+            .find(|c| c.name.as_deref() == Some(name))
+    }
+}
+
+/// How often a pattern is expected to match, checked by `scan_expecting`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScanExpectation {
+    /// Exactly once.
+    #[default]
+    Unique,
+    Exactly(usize),
+}
+
+impl ScanExpectation {
+    pub fn count(&self) -> usize {
+        match self {
+            Self::Unique => 1,
+            Self::Exactly(count) => *count,
+        }
+    }
+
+    /// Passes the results through if there's as many as expected.
+    pub fn check(&self, results: Vec<ScanResult>) -> Result<Vec<ScanResult>, ExpectationError> {
+        let count = results.len();
+        if count == self.count() {
+            return Ok(results);
+        }
+
+        let locations = results.iter()
+            .map(|r| r.location)
+            .collect();
+
+        match count {
+            0 => Err(ExpectationError::NotFound),
+            _ if count > self.count() => Err(ExpectationError::Ambiguous { count, locations }),
+            _ => Err(ExpectationError::TooFew { count, locations }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpectationError {
+    NotFound,
+    /// The pattern matched more often than expected.
+    Ambiguous { count: usize, locations: Vec<usize> },
+    /// The pattern matched, but not as often as expected.
+    TooFew { count: usize, locations: Vec<usize> },
+}
+
+impl fmt::Display for ExpectationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound => write!(f, "pattern was not found"),
+            Self::Ambiguous { count, locations } => {
+                write!(f, "pattern matched more often than expected, {count} times at {locations:#x?}")
+            },
+            Self::TooFew { count, locations } => {
+                write!(f, "pattern matched less often than expected, {count} times at {locations:#x?}")
+            },
+        }
+    }
+}
+
+impl Error for ExpectationError {}
+
+/// Restricts the offsets a match may start at to those where `offset % stride == phase`. Offsets
+/// are relative to the start of the scanned bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanOptions {
+    stride: usize,
+    phase: usize,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self { stride: 1, phase: 0 }
+    }
+}
+
+impl ScanOptions {
+    /// Only considers offsets that are a multiple of `stride` away from `phase`. Panics if `stride`
+    /// is zero.
+    pub fn with_stride(stride: usize, phase: usize) -> Self {
+        assert!(stride != 0, "stride must be non-zero");
+
+        Self { stride, phase: phase % stride }
+    }
+
+    /// Only considers offsets that are a multiple of `alignment`, for bytes that start at an
+    /// aligned address. Panics if `alignment` is zero.
+    pub fn aligned(alignment: usize) -> Self {
+        Self::with_stride(alignment, 0)
+    }
+
+    /// Only considers offsets whose address is a multiple of `alignment`, where `base` is the
+    /// address of the first byte. Panics if `alignment` is zero.
+    pub fn aligned_at(base: usize, alignment: usize) -> Self {
+        assert!(alignment != 0, "alignment must be non-zero");
+
+        Self::with_stride(alignment, alignment - base % alignment)
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    pub fn phase(&self) -> usize {
+        self.phase
+    }
+
+    /// Whether a match is allowed to start at the offset.
+    pub fn accepts(&self, offset: usize) -> bool {
+        offset % self.stride == self.phase
+    }
+
+    /// Whether every offset is considered.
+    pub fn is_unrestricted(&self) -> bool {
+        self.stride == 1
+    }
+}
+
+/// Determines how `scan_all` continues after it found a match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatchMode {
+    /// Continues searching after the end of the match, same as a global regex search.
+    #[default]
+    NonOverlapping,
+    /// Continues searching at the byte after the start of the match, reporting every offset the
+    /// pattern matches at.
+    Overlapping,
+}
@@ -0,0 +1,225 @@
+use std::mem;
+use std::panic;
+use std::thread;
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+
+use crate::scanner::threaded::ThreadedScanError;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Hands out the ids pools use to recognize their own workers.
+static NEXT_POOL_ID: AtomicUsize = AtomicUsize::new(1);
+
+thread_local! {
+    /// Id of the pool the current thread is a worker of, 0 for threads outside of any pool.
+    static CURRENT_POOL: Cell<usize> = const { Cell::new(0) };
+}
+
+/// A fixed set of worker threads that scans are distributed over. Spawning threads for every scan
+/// adds up when resolving a lot of patterns, so the threaded scanner keeps these around instead.
+pub struct ScanPool {
+    id: usize,
+    sender: Option<mpsc::Sender<Job>>,
+    workers: Vec<thread::JoinHandle<()>>,
+}
+
+impl ScanPool {
+    pub fn new(threads: usize) -> Result<Self, ThreadedScanError> {
+        if threads == 0 {
+            return Err(ThreadedScanError::ZeroParallelism);
+        }
+
+        let id = NEXT_POOL_ID.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let workers = (0..threads)
+            .map(|_| {
+                let receiver = receiver.clone();
+                thread::spawn(move || {
+                    CURRENT_POOL.with(|p| p.set(id));
+
+                    loop {
+                        // Hold the lock only for as long as it takes to pick up a job
+                        let job = receiver.lock().unwrap().recv();
+                        match job {
+                            Ok(job) => job(),
+                            // The pool was dropped
+                            Err(_) => break,
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        Ok(Self { id, sender: Some(sender), workers })
+    }
+
+    /// The pool used by the free-standing functions in `threaded`. Spawned on first use with a
+    /// thread for every unit of available parallelism.
+    pub fn global() -> &'static ScanPool {
+        static GLOBAL_POOL: OnceLock<ScanPool> = OnceLock::new();
+
+        GLOBAL_POOL.get_or_init(|| {
+            ScanPool::new(default_parallelism())
+                .expect("Default parallelism is never zero")
+        })
+    }
+
+    pub fn threads(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Runs every task on the pool and blocks until all of them are done. The results are returned
+    /// in the order of the tasks. Tasks are allowed to borrow from the caller as this doesn't return
+    /// before every task has finished. A panic in any of the tasks is resumed on the calling thread.
+    ///
+    /// When called from one of the pool's own workers, like a threaded scan started from within a
+    /// task, the tasks are run inline on that worker instead. Queueing them would have the worker
+    /// wait on jobs that might only ever be picked up by itself.
+    pub(crate) fn run<'s, T: Send + 's>(&self, tasks: Vec<Box<dyn FnOnce() -> T + Send + 's>>) -> Vec<T> {
+        if CURRENT_POOL.with(|p| p.get()) == self.id {
+            return tasks.into_iter()
+                .map(|task| task())
+                .collect();
+        }
+
+        let count = tasks.len();
+        let (result_sender, result_receiver) = mpsc::channel();
+        let mut pending = PendingJobs { sender: Some(result_sender), receiver: result_receiver };
+
+        for (index, task) in tasks.into_iter().enumerate() {
+            let result_sender = pending.sender.as_ref().unwrap().clone();
+            let job: Box<dyn FnOnce() + Send + 's> = Box::new(move || {
+                let result = panic::catch_unwind(panic::AssertUnwindSafe(task));
+                let _ = result_sender.send((index, result));
+            });
+
+            // SAFETY: the task is consumed before its result is sent and `pending` waits for every
+            // job that was queued, even when unwinding, so nothing the job borrows is accessed after
+            // this function returns.
+            let job = unsafe { mem::transmute::<Box<dyn FnOnce() + Send + 's>, Job>(job) };
+
+            self.sender.as_ref()
+                .unwrap()
+                .send(job)
+                .expect("Scan pool workers have exited");
+        }
+
+        drop(pending.sender.take());
+
+        let mut results = (0..count).map(|_| None).collect::<Vec<Option<T>>>();
+        let mut panic_payload = None;
+        for _ in 0..count {
+            let (index, result) = pending.receiver.recv()
+                .expect("Scan pool worker went away before finishing its job");
+
+            match result {
+                Ok(result) => results[index] = Some(result),
+                Err(payload) => panic_payload = Some(payload),
+            }
+        }
+
+        if let Some(payload) = panic_payload {
+            panic::resume_unwind(payload);
+        }
+
+        results.into_iter()
+            .map(|r| r.unwrap())
+            .collect()
+    }
+}
+
+/// Results of the jobs queued by `run`. Dropping it blocks until every queued job has either run or
+/// been dropped, which is what keeps the jobs from outliving what they borrow if `run` unwinds.
+struct PendingJobs<T> {
+    sender: Option<mpsc::Sender<(usize, thread::Result<T>)>>,
+    receiver: mpsc::Receiver<(usize, thread::Result<T>)>,
+}
+
+impl<T> Drop for PendingJobs<T> {
+    fn drop(&mut self) {
+        // Every job holds a sender, the channel disconnects once the last one is gone
+        drop(self.sender.take());
+        while self.receiver.recv().is_ok() { }
+    }
+}
+
+impl Drop for ScanPool {
+    fn drop(&mut self) {
+        // Closing the channel makes the workers exit their loop
+        drop(self.sender.take());
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+pub(crate) fn default_parallelism() -> usize {
+    thread::available_parallelism()
+        .map(|p| p.get())
+        .unwrap_or(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic;
+
+    use crate::scanner::pool::ScanPool;
+    use crate::scanner::threaded::ThreadedScanError;
+
+    #[test]
+    fn scan_pool_refuses_zero_threads() {
+        assert!(matches!(ScanPool::new(0), Err(ThreadedScanError::ZeroParallelism)));
+    }
+
+    #[test]
+    fn scan_pool_runs_borrowing_tasks_in_order() {
+        let pool = ScanPool::new(3).unwrap();
+        let data = (0..100).collect::<Vec<usize>>();
+
+        for _ in 0..10 {
+            let tasks = data.chunks(7)
+                .map(|c| Box::new(move || c.iter().sum::<usize>()) as Box<dyn FnOnce() -> usize + Send>)
+                .collect();
+
+            let results = pool.run(tasks);
+            assert_eq!(results, data.chunks(7).map(|c| c.iter().sum()).collect::<Vec<usize>>());
+        }
+    }
+
+    #[test]
+    fn scan_pool_resumes_task_panics_and_stays_usable() {
+        let pool = ScanPool::new(2).unwrap();
+
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            pool.run(vec![
+                Box::new(|| 1) as Box<dyn FnOnce() -> i32 + Send>,
+                Box::new(|| panic!("task failed")),
+            ])
+        }));
+        assert!(result.is_err());
+
+        assert_eq!(pool.run(vec![Box::new(|| 2) as Box<dyn FnOnce() -> i32 + Send>]), vec![2]);
+    }
+
+    #[test]
+    fn scan_pool_runs_nested_tasks_inline() {
+        let pool = ScanPool::new(1).unwrap();
+        let other = ScanPool::new(1).unwrap();
+
+        // With a single worker this would wait on itself if the inner tasks were queued
+        let results = pool.run(vec![
+            Box::new(|| {
+                let inner = pool.run(vec![Box::new(|| 1) as Box<dyn FnOnce() -> i32 + Send>, Box::new(|| 2)]);
+                let other = other.run(vec![Box::new(|| 3) as Box<dyn FnOnce() -> i32 + Send>]);
+                inner.into_iter().chain(other).sum::<i32>()
+            }) as Box<dyn FnOnce() -> i32 + Send>,
+        ]);
+
+        assert_eq!(results, vec![6]);
+    }
+}
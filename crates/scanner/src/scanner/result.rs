@@ -1,21 +1,171 @@
+use std::fmt;
+use std::mem;
+use std::iter;
+use std::error::Error;
+use std::ptr;
 use std::ops::Range;
+use crate::pattern::Pattern;
 use crate::scanner::ScanResult;
 
 #[derive(Debug, PartialEq)]
 pub struct ScanResultCapture {
     pub location: usize,
     pub bytes: Vec<u8>,
+    /// Name of the capture group in the pattern, if it has one.
+    pub name: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CaptureDecodeError {
+    /// The capture holds a different amount of bytes than the requested type is wide.
+    WidthMismatch {
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl fmt::Display for CaptureDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WidthMismatch { expected, actual } => write!(
+                f,
+                "capture is {actual} bytes wide but {expected} bytes were expected",
+            ),
+        }
+    }
+}
+
+impl Error for CaptureDecodeError {}
+
+/// Types that can be created from any sequence of bytes of the right size.
+///
+/// # Safety
+/// Implementors must be `Copy`, contain no padding and have every bit pattern be a valid value.
+pub unsafe trait Pod: Copy + 'static {}
+
+unsafe impl Pod for u8 {}
+unsafe impl Pod for u16 {}
+unsafe impl Pod for u32 {}
+unsafe impl Pod for u64 {}
+unsafe impl Pod for u128 {}
+unsafe impl Pod for usize {}
+unsafe impl Pod for i8 {}
+unsafe impl Pod for i16 {}
+unsafe impl Pod for i32 {}
+unsafe impl Pod for i64 {}
+unsafe impl Pod for i128 {}
+unsafe impl Pod for isize {}
+unsafe impl Pod for f32 {}
+unsafe impl Pod for f64 {}
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+impl ScanResultCapture {
+    /// Reinterprets the captured bytes as a `T` in the byte order of the host, unlike the `as_*`
+    /// accessors which always decode little-endian.
+    pub fn read_native<T: Pod>(&self) -> Result<T, CaptureDecodeError> {
+        self.check_width(mem::size_of::<T>())?;
+
+        // SAFETY: the width was checked above and `Pod` guarantees any bytes make a valid `T`
+        Ok(unsafe { ptr::read_unaligned(self.bytes.as_ptr() as *const T) })
+    }
+
+    pub fn as_u8(&self) -> Result<u8, CaptureDecodeError> {
+        Ok(u8::from_le_bytes(self.fixed()?))
+    }
+
+    pub fn as_u16(&self) -> Result<u16, CaptureDecodeError> {
+        Ok(u16::from_le_bytes(self.fixed()?))
+    }
+
+    pub fn as_u32(&self) -> Result<u32, CaptureDecodeError> {
+        Ok(u32::from_le_bytes(self.fixed()?))
+    }
+
+    pub fn as_u64(&self) -> Result<u64, CaptureDecodeError> {
+        Ok(u64::from_le_bytes(self.fixed()?))
+    }
+
+    pub fn as_i8(&self) -> Result<i8, CaptureDecodeError> {
+        Ok(i8::from_le_bytes(self.fixed()?))
+    }
+
+    pub fn as_i16(&self) -> Result<i16, CaptureDecodeError> {
+        Ok(i16::from_le_bytes(self.fixed()?))
+    }
+
+    /// Decodes the capture as a little-endian `i32`, which is what most displacements are.
+    pub fn as_i32(&self) -> Result<i32, CaptureDecodeError> {
+        Ok(i32::from_le_bytes(self.fixed()?))
+    }
+
+    pub fn as_i64(&self) -> Result<i64, CaptureDecodeError> {
+        Ok(i64::from_le_bytes(self.fixed()?))
+    }
+
+    pub fn as_f32(&self) -> Result<f32, CaptureDecodeError> {
+        Ok(f32::from_le_bytes(self.fixed()?))
+    }
+
+    pub fn as_f64(&self) -> Result<f64, CaptureDecodeError> {
+        Ok(f64::from_le_bytes(self.fixed()?))
+    }
+
+    fn fixed<const N: usize>(&self) -> Result<[u8; N], CaptureDecodeError> {
+        self.check_width(N)?;
+        Ok(self.bytes.as_slice().try_into().unwrap())
+    }
+
+    fn check_width(&self, expected: usize) -> Result<(), CaptureDecodeError> {
+        if self.bytes.len() != expected {
+            return Err(CaptureDecodeError::WidthMismatch {
+                expected,
+                actual: self.bytes.len(),
+            });
+        }
+
+        Ok(())
+    }
 }
 
 pub fn grab_captures(bytes: &[u8], groups: &[Range<usize>]) -> Vec<ScanResultCapture> {
     groups.iter()
         .map(|g| ScanResultCapture {
             location: g.start,
-            bytes: bytes[g.start..g.end].to_vec()
+            bytes: bytes[g.start..g.end].to_vec(),
+            name: None,
         })
         .collect()
 }
 
+/// Builds the result for a match of `pattern` at `location` in `bytes`.
+pub fn build_result(bytes: &[u8], pattern: &Pattern, location: usize) -> ScanResult {
+    let matched = &bytes[location..];
+
+    // Captures after a jump move along with the amount of bytes the jump skipped in this match
+    let jump_lengths = match pattern.jumps.is_empty() {
+        true => vec![],
+        false => pattern.jump_lengths(matched).unwrap_or_default(),
+    };
+
+    let groups = pattern.capture_groups.iter()
+        .map(|g| {
+            let start = pattern.offset_in_match(g.start, &jump_lengths);
+            start..start + g.len()
+        })
+        .collect::<Vec<Range<usize>>>();
+
+    let captures = grab_captures(matched, groups.as_slice())
+        .into_iter()
+        .zip(pattern.capture_names.iter().cloned().chain(iter::repeat(None)))
+        .map(|(c, name)| ScanResultCapture { name, ..rebase_capture(c, location) })
+        .collect();
+
+    ScanResult {
+        location,
+        captures,
+    }
+}
+
 pub fn rebase_result(input: ScanResult, offset: usize) -> ScanResult {
     ScanResult {
         location: input.location + offset,
@@ -28,6 +178,76 @@ pub fn rebase_result(input: ScanResult, offset: usize) -> ScanResult {
 pub fn rebase_capture(input: ScanResultCapture, offset: usize) -> ScanResultCapture {
     ScanResultCapture {
         location: input.location + offset,
-        bytes: input.bytes,
+        ..input
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pattern::Pattern;
+    use crate::scanner::ScanResult;
+    use crate::scanner::result::{build_result, CaptureDecodeError, ScanResultCapture};
+
+    fn capture(bytes: &[u8]) -> ScanResultCapture {
+        ScanResultCapture { location: 0, bytes: bytes.to_vec(), name: None }
+    }
+
+    #[test]
+    fn typed_accessors_decode_little_endian() {
+        assert_eq!(capture(&[0x7F]).as_u8(), Ok(0x7F));
+        assert_eq!(capture(&[0xFF]).as_i8(), Ok(-1));
+        assert_eq!(capture(&[0x34, 0x12]).as_u16(), Ok(0x1234));
+        assert_eq!(capture(&[0xFE, 0xFF]).as_i16(), Ok(-2));
+        assert_eq!(capture(&[0x78, 0x56, 0x34, 0x12]).as_u32(), Ok(0x12345678));
+        assert_eq!(capture(&[0xF0, 0xFF, 0xFF, 0xFF]).as_i32(), Ok(-16));
+        assert_eq!(capture(&[0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01]).as_u64(), Ok(0x0102030405060708));
+        assert_eq!(capture(&[0xFF; 8]).as_i64(), Ok(-1));
+        assert_eq!(capture(&[0x00, 0x00, 0x80, 0x3F]).as_f32(), Ok(1.0));
+        assert_eq!(capture(&[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xF0, 0x3F]).as_f64(), Ok(1.0));
+    }
+
+    #[test]
+    fn typed_accessors_reject_mismatching_widths() {
+        assert_eq!(
+            capture(&[0x00, 0x00, 0x00]).as_u32(),
+            Err(CaptureDecodeError::WidthMismatch { expected: 4, actual: 3 }),
+        );
+        assert_eq!(
+            capture(&[0x00; 8]).as_i32(),
+            Err(CaptureDecodeError::WidthMismatch { expected: 4, actual: 8 }),
+        );
+        assert_eq!(
+            capture(&[0x00; 3]).read_native::<[u16; 2]>(),
+            Err(CaptureDecodeError::WidthMismatch { expected: 4, actual: 3 }),
+        );
+    }
+
+    #[test]
+    fn read_native_works_with_arrays() {
+        let pair = [1u16.to_ne_bytes(), 2u16.to_ne_bytes()].concat();
+        assert_eq!(capture(&pair).read_native::<[u16; 2]>(), Ok([1, 2]));
+        assert_eq!(capture(&1u32.to_ne_bytes()).read_native::<u32>(), Ok(1));
+    }
+
+    #[test]
+    fn scan_result_capture_helper_works() {
+        let result = ScanResult {
+            location: 0,
+            captures: vec![capture(&[0x01]), capture(&[0x02, 0x00])],
+        };
+
+        assert_eq!(result.capture(1).unwrap().as_u16(), Ok(2));
+        assert!(result.capture(2).is_none());
+    }
+
+    #[test]
+    fn scan_result_named_helper_works() {
+        let pattern = Pattern::from_byte_pattern("01 [02] [vtable: 03 04]").unwrap();
+        let result = build_result(&[0x00, 0x01, 0x02, 0x03, 0x04], &pattern, 1);
+
+        assert_eq!(result.capture(0).unwrap().name, None);
+        assert_eq!(result.named("vtable").unwrap().location, 3);
+        assert_eq!(result.named("vtable").unwrap().as_u16(), Ok(0x0403));
+        assert!(result.named("missing").is_none());
     }
 }
@@ -0,0 +1,391 @@
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+use crate::pattern::Pattern;
+use crate::scanner::{ExpectationError, MatchMode, ScanExpectation, ScanResult};
+use crate::scanner::result::build_result;
+
+/// Byte values that show up a lot in code sections (padding, common prefixes, zeroes). We'd
+/// rather not pick these as the anchor as they yield a lot of candidates that need verifying.
+const COMMON_BYTES: [u8; 6] = [0x00, 0xFF, 0xCC, 0x48, 0x8B, 0x89];
+
+/// Scans for the first occurrence of `pattern` in `bytes`. Candidates are located by comparing
+/// one fully-masked byte of the pattern (the anchor) against 16 or 32 bytes at a time, after which
+/// the rest of the pattern is verified with masked compares.
+pub fn scan(bytes: &[u8], pattern: &Pattern) -> Option<ScanResult> {
+    find_from(bytes, pattern, 0)
+        .map(|offset| build_result(bytes, pattern, offset))
+}
+
+/// Scans for a pattern that should match exactly once. Overlapping matches count towards the
+/// amount of matches.
+pub fn scan_unique(bytes: &[u8], pattern: &Pattern) -> Result<ScanResult, ExpectationError> {
+    scan_expecting(bytes, pattern, ScanExpectation::Unique)
+        .map(|mut results| results.remove(0))
+}
+
+/// Scans for all matches and checks that there's as many as expected. Overlapping matches count
+/// towards the amount of matches.
+pub fn scan_expecting(
+    bytes: &[u8],
+    pattern: &Pattern,
+    expectation: ScanExpectation,
+) -> Result<Vec<ScanResult>, ExpectationError> {
+    expectation.check(scan_all(bytes, pattern, MatchMode::Overlapping))
+}
+
+pub fn scan_all(bytes: &[u8], pattern: &Pattern, mode: MatchMode) -> Vec<ScanResult> {
+    let mut results = Vec::new();
+
+    let mut current_offset = 0;
+    while let Some(offset) = find_from(bytes, pattern, current_offset) {
+        results.push(build_result(bytes, pattern, offset));
+
+        current_offset = match mode {
+            // Move cursor to the end of the match
+            MatchMode::NonOverlapping => offset + pattern.match_length(&bytes[offset..]),
+            MatchMode::Overlapping => offset + 1,
+        };
+    }
+
+    results
+}
+
+/// Finds the offset of the first match that starts at or after `start`.
+fn find_from(bytes: &[u8], pattern: &Pattern, start: usize) -> Option<usize> {
+    if pattern.length == 0 || bytes.len() < pattern.min_length() {
+        return None;
+    }
+
+    // Last offset at which the pattern still fits in the slice
+    let last_offset = bytes.len() - pattern.min_length();
+    if start > last_offset {
+        return None;
+    }
+
+    let anchor = match select_anchor(pattern) {
+        Some(anchor) => anchor,
+
+        // Without a fully-masked byte there's nothing to compare against so we check every offset
+        None => return (start..=last_offset).find(|o| pattern.matches(&bytes[*o..])),
+    };
+
+    let needle = pattern.bytes[anchor];
+    let mut offset = start;
+    while offset <= last_offset {
+        // Only search the part of the slice where the anchor can occur for a match that fits
+        let haystack = &bytes[offset + anchor..=last_offset + anchor];
+        let candidate = offset + find_byte(haystack, needle)?;
+
+        if pattern.matches(&bytes[candidate..]) {
+            return Some(candidate);
+        }
+
+        offset = candidate + 1;
+    }
+
+    None
+}
+
+/// Picks the index of the byte in the pattern used to find candidates. Prefers fully-masked bytes
+/// that aren't too common in executable code. Only bytes in front of the first jump are at a fixed
+/// distance from the start of a match, and bytes with alternatives don't have a single value.
+fn select_anchor(pattern: &Pattern) -> Option<usize> {
+    let mut fully_masked = pattern.mask[..pattern.prefix_length()].iter()
+        .enumerate()
+        .filter(|(i, m)| **m == 0xFF && pattern.alternatives_at(*i).is_empty())
+        .map(|(i, _)| i)
+        .peekable();
+
+    let first = *fully_masked.peek()?;
+    Some(
+        fully_masked.find(|i| !COMMON_BYTES.contains(&pattern.bytes[*i]))
+            .unwrap_or(first)
+    )
+}
+
+/// Finds the first occurrence of `needle` in `haystack` using the widest vector instructions the
+/// CPU supports.
+fn find_byte(haystack: &[u8], needle: u8) -> Option<usize> {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return unsafe { find_byte_avx2(haystack, needle) };
+        }
+
+        // SSE2 is part of the x86_64 baseline so there is no need to detect it
+        unsafe { find_byte_sse2(haystack, needle) }
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    find_byte_portable(haystack, needle)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn find_byte_avx2(haystack: &[u8], needle: u8) -> Option<usize> {
+    let splat = _mm256_set1_epi8(needle as i8);
+
+    let mut offset = 0;
+    while offset + 32 <= haystack.len() {
+        let chunk = _mm256_loadu_si256(haystack.as_ptr().add(offset) as *const __m256i);
+        let hits = _mm256_movemask_epi8(_mm256_cmpeq_epi8(chunk, splat)) as u32;
+        if hits != 0 {
+            return Some(offset + hits.trailing_zeros() as usize);
+        }
+
+        offset += 32;
+    }
+
+    // Let the narrower implementation deal with the tail
+    find_byte_sse2(&haystack[offset..], needle)
+        .map(|p| p + offset)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn find_byte_sse2(haystack: &[u8], needle: u8) -> Option<usize> {
+    let splat = _mm_set1_epi8(needle as i8);
+
+    let mut offset = 0;
+    while offset + 16 <= haystack.len() {
+        let chunk = _mm_loadu_si128(haystack.as_ptr().add(offset) as *const __m128i);
+        let hits = _mm_movemask_epi8(_mm_cmpeq_epi8(chunk, splat)) as u32;
+        if hits != 0 {
+            return Some(offset + hits.trailing_zeros() as usize);
+        }
+
+        offset += 16;
+    }
+
+    find_byte_portable(&haystack[offset..], needle)
+        .map(|p| p + offset)
+}
+
+fn find_byte_portable(haystack: &[u8], needle: u8) -> Option<usize> {
+    haystack.iter().position(|b| *b == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use crate::scanner;
+    use crate::scanner::MatchMode;
+    use crate::pattern::Pattern;
+
+    #[test]
+    fn simd_scanner_behaves_with_empty_slice() {
+        let pattern = Pattern::from_byte_slice(&[0xAA, 0xAA, 0xAA, 0xAA, 0xAA]);
+        let slice: &[u8] = &[];
+        let result = scanner::simd::scan(slice, &pattern);
+
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn simd_scanner_behaves_with_too_long_of_a_pattern() {
+        let pattern = Pattern::from_byte_slice(&[0xAA, 0xAA, 0xAA, 0xAA, 0xAA]);
+        let slice: &[u8] = &[0x00, 0x00, 0x00, 0x00];
+        let result = scanner::simd::scan(slice, &pattern);
+
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn simd_scanner_finds_the_pattern_1() {
+        let pattern = Pattern::from_byte_slice(&[0x75, 0x84, 0x4A, 0xEF, 0x23, 0x24, 0xCA, 0x35]);
+        let randomness = include_bytes!("../../test/random.bin");
+        let result = scanner::simd::scan(randomness, &pattern).unwrap();
+
+        assert_eq!(result.location, 1309924);
+        assert_eq!(result.captures.len(), 0);
+    }
+
+    #[test]
+    fn simd_scanner_finds_the_pattern_2() {
+        let pattern = Pattern::from_byte_pattern("B7 [?? CF D8 ??] 0A ?? 27").unwrap();
+        let randomness = include_bytes!("../../test/random.bin");
+        let result = scanner::simd::scan(randomness, &pattern).unwrap();
+
+        assert_eq!(result.location, 867776);
+        assert_eq!(result.captures.len(), 1);
+
+        assert_eq!(result.captures[0].location, 867777);
+        assert_eq!(result.captures[0].bytes, vec![0xc6, 0xcf, 0xd8, 0x11]);
+    }
+
+    #[test]
+    fn simd_scanner_doesnt_find_the_pattern() {
+        let pattern = Pattern::from_byte_slice(&[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+        let randomness = include_bytes!("../../test/random.bin");
+        let result = scanner::simd::scan(randomness, &pattern);
+
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn simd_scanner_can_scan_all() {
+        let pattern = Pattern::from_byte_slice(&[0x09, 0x02]);
+        let randomness = include_bytes!("../../test/random.bin");
+        let result = scanner::simd::scan_all(randomness, &pattern, MatchMode::NonOverlapping);
+
+        assert_eq!(result.len(), 35);
+    }
+
+    #[test]
+    fn simd_scanner_handles_patterns_without_fully_masked_bytes() {
+        let pattern = Pattern::from_byte_pattern("m1011???? ?? [m??001111]").unwrap();
+        let randomness = include_bytes!("../../test/random.bin");
+
+        assert_eq!(
+            scanner::simd::scan(randomness, &pattern),
+            scanner::simple::scan(randomness, &pattern),
+        );
+    }
+
+    #[test]
+    fn simd_scanner_finds_patterns_with_jumps_and_alternatives() {
+        let randomness = include_bytes!("../../test/random.bin");
+
+        let patterns = [
+            Pattern::from_byte_pattern("B7 [??] {1-6} D8 [??] 0A {1} 27").unwrap(),
+            Pattern::from_byte_pattern("09 {0-12} [02] {1-3} ??").unwrap(),
+            Pattern::from_byte_pattern("(B7|09) ?? (CF|02) ?F").unwrap(),
+        ];
+
+        for pattern in patterns {
+            for mode in [MatchMode::NonOverlapping, MatchMode::Overlapping] {
+                assert_eq!(
+                    scanner::simd::scan_all(randomness, &pattern, mode),
+                    scanner::simple::scan_all(randomness, &pattern, mode),
+                    "{mode:?} scan_all result differs for pattern {pattern:?}",
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn simd_scanner_matches_simple_scanner_on_random_patterns() {
+        let randomness = include_bytes!("../../test/random.bin");
+        let mut rng = StdRng::seed_from_u64(0x62726f6164);
+
+        for _ in 0..16 {
+            // Lift the pattern from the data so that it's guaranteed to occur at least once
+            let length = rng.gen_range(3..16);
+            let start = rng.gen_range(0..randomness.len() - length);
+            let mut pattern = Pattern::from_byte_slice(&randomness[start..start + length]);
+
+            // Wildcard a couple of bytes but keep the first one intact
+            for i in 1..length {
+                if rng.gen_bool(0.2) {
+                    pattern.bytes[i] = 0x00;
+                    pattern.mask[i] = 0x00;
+                }
+            }
+
+            let capture_start = rng.gen_range(0..length);
+            let capture_end = rng.gen_range(capture_start..=length);
+            pattern.capture_groups.push(capture_start..capture_end);
+
+            assert_eq!(
+                scanner::simd::scan(randomness, &pattern),
+                scanner::simple::scan(randomness, &pattern),
+                "scan result differs for pattern {pattern:?}",
+            );
+
+            for mode in [MatchMode::NonOverlapping, MatchMode::Overlapping] {
+                assert_eq!(
+                    scanner::simd::scan_all(randomness, &pattern, mode),
+                    scanner::simple::scan_all(randomness, &pattern, mode),
+                    "{mode:?} scan_all result differs for pattern {pattern:?}",
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn simd_scanner_matches_simple_scanner_on_generated_buffers() {
+        let mut rng = StdRng::seed_from_u64(0x6275666665727321);
+        let mut unanchored = 0;
+
+        for iteration in 0..5000 {
+            // A small alphabet makes for plenty of candidates and matches. The lengths don't line up
+            // with the vector widths and the slice is skewed so it doesn't start aligned either.
+            let length = rng.gen_range(0..300);
+            let buffer = (0..length)
+                .map(|_| match rng.gen_bool(0.1) {
+                    true => 0xCC,
+                    false => rng.gen_range(0..4),
+                })
+                .collect::<Vec<u8>>();
+            let bytes = &buffer[rng.gen_range(0..4).min(buffer.len())..];
+
+            let pattern_length = rng.gen_range(1..8);
+            let mut pattern = match bytes.len() > pattern_length && rng.gen_bool(0.5) {
+                true => {
+                    let start = rng.gen_range(0..bytes.len() - pattern_length);
+                    Pattern::from_byte_slice(&bytes[start..start + pattern_length])
+                },
+                false => Pattern::from_byte_vec((0..pattern_length).map(|_| rng.gen_range(0..4)).collect()),
+            };
+
+            // Every third pattern has no fully-masked byte, which leaves only the fallback path
+            let without_anchor = iteration % 3 == 0;
+            for i in 0..pattern_length {
+                let mask = match rng.gen_range(0..5) {
+                    0 => 0x00,
+                    1 => rng.gen_range(0x01..0xFF),
+                    _ if without_anchor => rng.gen_range(0x00..0xFF),
+                    _ => 0xFF,
+                };
+
+                pattern.mask[i] = mask;
+                pattern.bytes[i] &= mask;
+            }
+
+            if super::select_anchor(&pattern).is_none() {
+                unanchored += 1;
+            }
+
+            assert_eq!(
+                scanner::simd::scan(bytes, &pattern),
+                scanner::simple::scan(bytes, &pattern),
+                "scan result differs for pattern {pattern:?} on {bytes:?}",
+            );
+
+            for mode in [MatchMode::NonOverlapping, MatchMode::Overlapping] {
+                assert_eq!(
+                    scanner::simd::scan_all(bytes, &pattern, mode),
+                    scanner::simple::scan_all(bytes, &pattern, mode),
+                    "{mode:?} scan_all result differs for pattern {pattern:?} on {bytes:?}",
+                );
+            }
+        }
+
+        assert!(unanchored >= 1000, "only {unanchored} patterns went through the fallback path");
+    }
+
+    #[test]
+    fn find_byte_backends_agree() {
+        let mut rng = StdRng::seed_from_u64(0x73776f7264);
+
+        for _ in 0..1000 {
+            let length = rng.gen_range(0..200);
+            let haystack = (0..length).map(|_| rng.gen_range(0..8)).collect::<Vec<u8>>();
+            let needle = rng.gen_range(0..8);
+            let expected = super::find_byte_portable(&haystack, needle);
+
+            assert_eq!(super::find_byte(&haystack, needle), expected);
+
+            #[cfg(target_arch = "x86_64")]
+            {
+                assert_eq!(unsafe { super::find_byte_sse2(&haystack, needle) }, expected);
+                if is_x86_feature_detected!("avx2") {
+                    assert_eq!(unsafe { super::find_byte_avx2(&haystack, needle) }, expected);
+                }
+            }
+        }
+    }
+}
@@ -1,81 +1,345 @@
+use std::ops::ControlFlow;
+use std::collections::HashMap;
 
-use crate::pattern::Pattern;
-use crate::scanner::ScanResult;
-use crate::scanner::result::{grab_captures, rebase_capture};
+use crate::pattern::{Pattern, PatternSet};
+use crate::scanner::{ExpectationError, MatchMode, ScanExpectation, ScanOptions, ScanResult};
+use crate::scanner::result::build_result;
 
-pub fn scan(bytes: &'static [u8], pattern: &Pattern) -> Option<ScanResult> {
-    let mut position_in_pattern = 0;
+/// Amount of bytes `scan_reverse` looks at before moving further back, doubles with every block.
+const REVERSE_BLOCK_SIZE: usize = 0x1000;
 
+pub fn scan(bytes: &[u8], pattern: &Pattern) -> Option<ScanResult> {
+    scan_with_options(bytes, pattern, &ScanOptions::default())
+}
+
+/// Scans for the first match that starts at an offset the options accept.
+pub fn scan_with_options(bytes: &[u8], pattern: &Pattern, options: &ScanOptions) -> Option<ScanResult> {
+    let mut result = None;
+
+    for_each_match(bytes, pattern, MatchMode::NonOverlapping, options, |offset| {
+        result = Some(build_result(bytes, pattern, offset));
+        ControlFlow::Break(())
+    });
+
+    result
+}
+
+/// Scans for a pattern that should match exactly once. Overlapping matches count towards the
+/// amount of matches.
+pub fn scan_unique(bytes: &[u8], pattern: &Pattern) -> Result<ScanResult, ExpectationError> {
+    scan_expecting(bytes, pattern, ScanExpectation::Unique)
+        .map(|mut results| results.remove(0))
+}
+
+/// Scans for all matches and checks that there's as many as expected. Overlapping matches count
+/// towards the amount of matches.
+pub fn scan_expecting(
+    bytes: &[u8],
+    pattern: &Pattern,
+    expectation: ScanExpectation,
+) -> Result<Vec<ScanResult>, ExpectationError> {
+    expectation.check(scan_all(bytes, pattern, MatchMode::Overlapping))
+}
+
+pub fn scan_all(bytes: &[u8], pattern: &Pattern, mode: MatchMode) -> Vec<ScanResult> {
+    scan_all_with_options(bytes, pattern, mode, &ScanOptions::default())
+}
+
+/// Scans for all matches that start at an offset the options accept. Matches at other offsets
+/// don't prevent overlapping matches from being found.
+pub fn scan_all_with_options(
+    bytes: &[u8],
+    pattern: &Pattern,
+    mode: MatchMode,
+    options: &ScanOptions,
+) -> Vec<ScanResult> {
+    let mut results = Vec::new();
+
+    for_each_match(bytes, pattern, mode, options, |offset| {
+        results.push(build_result(bytes, pattern, offset));
+        ControlFlow::Continue(())
+    });
+
+    results
+}
+
+/// Walks backwards from `from` and returns the closest match that starts at or before it. Matches
+/// are allowed to extend past `from`.
+pub fn scan_reverse(bytes: &[u8], pattern: &Pattern, from: usize) -> Option<ScanResult> {
+    scan_reverse_with_options(bytes, pattern, from, &ScanOptions::default())
+}
+
+/// Same as `scan_reverse` but only considers offsets the options accept.
+pub fn scan_reverse_with_options(
+    bytes: &[u8],
+    pattern: &Pattern,
+    from: usize,
+    options: &ScanOptions,
+) -> Option<ScanResult> {
+    if bytes.is_empty() || pattern.length == 0 {
+        return None;
+    }
+
+    // Matches have to start before this
+    let mut block_end = from.min(bytes.len() - 1) + 1;
+    let mut block_size = REVERSE_BLOCK_SIZE;
+
+    while block_end > 0 {
+        // Blocks start at a multiple of the stride so offsets in the block are accepted the same
+        let block_start = block_end.saturating_sub(block_size) / options.stride() * options.stride();
+        let block = &bytes[block_start..(block_end + pattern.max_length() - 1).min(bytes.len())];
+
+        let mut last = None;
+        for_each_match(block, pattern, MatchMode::Overlapping, options, |offset| {
+            if block_start + offset >= block_end {
+                return ControlFlow::Break(());
+            }
+
+            last = Some(block_start + offset);
+            ControlFlow::Continue(())
+        });
+
+        if let Some(location) = last {
+            return Some(build_result(bytes, pattern, location));
+        }
+
+        block_end = block_start;
+        block_size *= 2;
+    }
+
+    None
+}
+
+/// Finds all matches, the last match comes first. With `MatchMode::NonOverlapping` matches are
+/// dropped if they overlap with a match after them.
+pub fn scan_all_reverse(bytes: &[u8], pattern: &Pattern, mode: MatchMode) -> Vec<ScanResult> {
+    let mut results = scan_all(bytes, pattern, MatchMode::Overlapping);
+    results.reverse();
+
+    if mode == MatchMode::NonOverlapping {
+        let mut next_end = usize::MAX;
+        results.retain(|r| {
+            if r.location + pattern.match_length(&bytes[r.location..]) > next_end {
+                return false;
+            }
+
+            next_end = r.location;
+            true
+        });
+    }
+
+    results
+}
+
+/// Scans for all patterns in the set in a single pass over `bytes`. Every name in the set is
+/// present in the result, names that didn't match map to an empty list.
+pub fn scan_set(
+    bytes: &[u8],
+    set: &PatternSet,
+    mode: MatchMode,
+) -> HashMap<String, Vec<ScanResult>> {
+    let mut results = set.iter()
+        .map(|(name, _)| (name.to_string(), Vec::new()))
+        .collect::<HashMap<String, Vec<ScanResult>>>();
+
+    // Tracks where the last match of every pattern ended
+    let mut next_allowed = vec![0; set.len()];
+    set.for_each_match(bytes, |index, offset| {
+        let (name, pattern) = set.entry(index);
+
+        if mode == MatchMode::NonOverlapping {
+            if offset < next_allowed[index] {
+                return;
+            }
+
+            next_allowed[index] = offset + pattern.match_length(&bytes[offset..]);
+        }
+
+        results.get_mut(name)
+            .unwrap()
+            .push(build_result(bytes, pattern, offset));
+    });
+
+    results
+}
+
+/// Calls `on_match` with the offset of every match in `bytes` the options accept, in order.
+fn for_each_match(
+    bytes: &[u8],
+    pattern: &Pattern,
+    mode: MatchMode,
+    options: &ScanOptions,
+    mut on_match: impl FnMut(usize) -> ControlFlow<()>,
+) {
+    if pattern.length == 0 {
+        return;
+    }
+
+    if options.is_unrestricted() {
+        return for_each_unrestricted_match(bytes, pattern, mode, on_match);
+    }
+
+    // Matches at rejected offsets can't be allowed to reset the matcher, so every match is looked
+    // at and overlapping ones are dropped here instead.
+    let mut next_allowed = 0;
+    for_each_unrestricted_match(bytes, pattern, MatchMode::Overlapping, |offset| {
+        if offset < next_allowed || !options.accepts(offset) {
+            return ControlFlow::Continue(());
+        }
+
+        if mode == MatchMode::NonOverlapping {
+            next_allowed = offset + pattern.match_length(&bytes[offset..]);
+        }
+
+        on_match(offset)
+    });
+}
+
+fn for_each_unrestricted_match(
+    bytes: &[u8],
+    pattern: &Pattern,
+    mode: MatchMode,
+    on_match: impl FnMut(usize) -> ControlFlow<()>,
+) {
+    match pattern.jumps.is_empty() {
+        true => for_each_fixed_match(bytes, pattern, mode, on_match),
+        false => for_each_jump_match(bytes, pattern, mode, on_match),
+    }
+}
+
+/// Uses a bit-parallel shift-and matcher: bit `i` of the state is set when the last `i + 1` bytes
+/// match the first `i + 1` positions of the pattern. Every byte is looked at exactly once and the
+/// state tracks every offset at the same time, which makes it equivalent to doing a masked compare
+/// of the entire pattern at each offset without ever having to backtrack.
+fn for_each_fixed_match(
+    bytes: &[u8],
+    pattern: &Pattern,
+    mode: MatchMode,
+    mut on_match: impl FnMut(usize) -> ControlFlow<()>,
+) {
+    let table = ShiftAndTable::new(pattern);
+    let last_bit = 1u64 << ((pattern.length - 1) % 64);
+
+    // Most patterns fit in a single word, which saves us from carrying bits between words
+    if table.words == 1 {
+        let mut state = 0u64;
+        for (position, byte) in bytes.iter().enumerate() {
+            state = ((state << 1) | 1) & table.row(*byte)[0];
+
+            if state & last_bit != 0 {
+                if on_match(position + 1 - pattern.length).is_break() {
+                    return;
+                }
+
+                if mode == MatchMode::NonOverlapping {
+                    state = 0;
+                }
+            }
+        }
+
+        return;
+    }
+
+    let last_word = table.words - 1;
+    let mut state = vec![0u64; table.words];
     for (position, byte) in bytes.iter().enumerate() {
-        // Reset position in pattern if current byte is not masked off and doesn't match
-        // expected byte.
-        if pattern.bytes[position_in_pattern] != *byte & pattern.mask[position_in_pattern] {
-            position_in_pattern = 0;
-            continue;
+        let row = table.row(*byte);
+
+        // Shift every partial match one position further into the pattern and start a new one at
+        // the current byte. Only the partial matches the current byte agrees with survive.
+        let mut carry = 1u64;
+        for (word, allowed) in state.iter_mut().zip(row.iter()) {
+            let next_carry = *word >> 63;
+            *word = ((*word << 1) | carry) & allowed;
+            carry = next_carry;
         }
 
         // Check if all bytes in the pattern have been matched.
-        if position_in_pattern == pattern.length - 1 {
+        if state[last_word] & last_bit != 0 {
             // Offset in the bytes the match was found
-            let match_offset = position - pattern.length + 1;
-
-            // Grab data for any capture groups
-            let captures = grab_captures(
-                    &bytes[match_offset..match_offset + pattern.length],
-                    pattern.capture_groups.as_slice()
-                )
-                .into_iter()
-                .map(|c| rebase_capture(c, match_offset))
-                .collect();
-
-            return Some(ScanResult {
-                location: match_offset,
-                captures,
-            });
-        }
+            let match_offset = position + 1 - pattern.length;
+            if on_match(match_offset).is_break() {
+                return;
+            }
 
-        position_in_pattern += 1;
+            // Drop partial matches that overlap with this one
+            if mode == MatchMode::NonOverlapping {
+                state.fill(0);
+            }
+        }
     }
+}
 
-    None
+/// Patterns with jumps don't have a fixed length, so the shift-and matcher only looks for the bytes
+/// in front of the first jump. The rest of the pattern is checked wherever those turn up.
+fn for_each_jump_match(
+    bytes: &[u8],
+    pattern: &Pattern,
+    mode: MatchMode,
+    mut on_match: impl FnMut(usize) -> ControlFlow<()>,
+) {
+    let mut next_allowed = 0;
+
+    for_each_fixed_match(bytes, &pattern.prefix(), MatchMode::Overlapping, |offset| {
+        if offset < next_allowed {
+            return ControlFlow::Continue(());
+        }
+
+        let Some(jump_lengths) = pattern.jump_lengths(&bytes[offset..]) else {
+            return ControlFlow::Continue(());
+        };
+
+        if mode == MatchMode::NonOverlapping {
+            next_allowed = offset + pattern.length + jump_lengths.iter().sum::<usize>();
+        }
+
+        on_match(offset)
+    });
 }
 
-pub fn scan_all(bytes: &'static [u8], pattern: &Pattern) -> Vec<ScanResult> {
-    let mut results = Vec::new();
+/// For every possible byte value, holds the set of pattern positions that accept that byte.
+struct ShiftAndTable {
+    words: usize,
+    bits: Vec<u64>,
+}
 
-    let mut current_offset = 0;
-    loop {
-        let search_area = &bytes[current_offset..];
-        match scan(search_area, pattern) {
-            Some(mut occurence) => {
-                // Rebase occurence locations to absolute location in slice 
-                let rebased_location = occurence.location + current_offset;
-                for capture in occurence.captures.iter_mut() {
-                    capture.location += current_offset;
-                }
+impl ShiftAndTable {
+    fn new(pattern: &Pattern) -> Self {
+        let words = pattern.length.div_ceil(64);
+        let mut bits = vec![0u64; 256 * words];
 
-                // Move cursor to the end of the match
-                current_offset = current_offset + occurence.location + pattern.length;
+        for value in 0..=255u8 {
+            let row = &mut bits[value as usize * words..(value as usize + 1) * words];
 
-                occurence.location = rebased_location;
-                results.push(occurence);
+            for i in 0..pattern.length {
+                if pattern.accepts(i, value) {
+                    row[i / 64] |= 1 << (i % 64);
+                }
             }
-            None => break
         }
+
+        Self { words, bits }
     }
 
-    results
+    fn row(&self, value: u8) -> &[u64] {
+        let start = value as usize * self.words;
+        &self.bits[start..start + self.words]
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
     use crate::scanner;
-    use crate::pattern::Pattern;
+    use crate::scanner::{ExpectationError, MatchMode, ScanExpectation, ScanOptions};
+    use crate::pattern::{Pattern, PatternSet};
 
     #[test]
     fn simple_scanner_behaves_with_empty_slice() {
         let pattern = Pattern::from_byte_slice(&[0xAA, 0xAA, 0xAA, 0xAA, 0xAA]);
-        let slice = Box::leak(Box::new([]));
+        let slice: &[u8] = &[];
         let result = scanner::simple::scan(slice, &pattern);
 
         assert_eq!(result, None);
@@ -84,7 +348,16 @@ mod tests {
     #[test]
     fn simple_scanner_behaves_with_too_long_of_a_pattern() {
         let pattern = Pattern::from_byte_slice(&[0xAA, 0xAA, 0xAA, 0xAA, 0xAA]);
-        let slice = Box::leak(Box::new([0x00, 0x00, 0x00, 0x00]));
+        let slice: &[u8] = &[0x00, 0x00, 0x00, 0x00];
+        let result = scanner::simple::scan(slice, &pattern);
+
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn simple_scanner_behaves_with_empty_pattern() {
+        let pattern = Pattern::from_byte_slice(&[]);
+        let slice: &[u8] = &[0x00, 0x00, 0x00, 0x00];
         let result = scanner::simple::scan(slice, &pattern);
 
         assert_eq!(result, None);
@@ -126,8 +399,358 @@ mod tests {
     fn simple_scanner_can_scan_all() {
         let pattern = Pattern::from_byte_slice(&[0x09, 0x02]);
         let randomness = include_bytes!("../../test/random.bin");
-        let result = scanner::simple::scan_all(randomness, &pattern);
+        let result = scanner::simple::scan_all(randomness, &pattern, MatchMode::NonOverlapping);
 
         assert_eq!(result.len(), 35);
     }
+
+    #[test]
+    fn simple_scanner_scans_owned_buffers() {
+        let buffer = vec![0x00, 0x01, 0x02, 0x03, 0x04];
+        let pattern = Pattern::from_byte_pattern("02 [03]").unwrap();
+        let result = scanner::simple::scan(&buffer, &pattern).unwrap();
+
+        assert_eq!(result.location, 2);
+        assert_eq!(result.captures[0].bytes, vec![0x03]);
+    }
+
+    #[test]
+    fn simple_scanner_rechecks_the_mismatching_byte() {
+        let pattern = Pattern::from_byte_pattern("AA AB").unwrap();
+        let slice: &[u8] = &[0xAA, 0xAA, 0xAB];
+        let result = scanner::simple::scan(slice, &pattern).unwrap();
+
+        assert_eq!(result.location, 1);
+    }
+
+    #[test]
+    fn simple_scanner_matches_wildcard_heavy_patterns() {
+        let pattern = Pattern::from_byte_pattern("?? ?? AB").unwrap();
+        let slice: &[u8] = &[0x00, 0x01, 0x02, 0xAB];
+        let result = scanner::simple::scan(slice, &pattern).unwrap();
+
+        assert_eq!(result.location, 1);
+    }
+
+    #[test]
+    fn simple_scanner_handles_long_patterns() {
+        let randomness = include_bytes!("../../test/random.bin");
+        let pattern = Pattern::from_byte_slice(&randomness[1000..1200]);
+        let result = scanner::simple::scan(randomness, &pattern).unwrap();
+
+        assert_eq!(result.location, 1000);
+    }
+
+    #[test]
+    fn simple_scanner_can_scan_all_overlapping() {
+        let pattern = Pattern::from_byte_pattern("AA ?? AA").unwrap();
+        let slice: &[u8] = &[0xAA, 0xAA, 0xAA, 0xAA, 0xAA];
+
+        let non_overlapping = scanner::simple::scan_all(slice, &pattern, MatchMode::NonOverlapping);
+        assert_eq!(non_overlapping.iter().map(|r| r.location).collect::<Vec<_>>(), vec![0]);
+
+        let overlapping = scanner::simple::scan_all(slice, &pattern, MatchMode::Overlapping);
+        assert_eq!(overlapping.iter().map(|r| r.location).collect::<Vec<_>>(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn simple_scanner_matches_naive_scanner_on_random_input() {
+        let mut rng = StdRng::seed_from_u64(0x73696d706c65);
+
+        for _ in 0..2000 {
+            // Small alphabets make for lots of partial and overlapping matches
+            let alphabet = rng.gen_range(1..4u8);
+            let bytes = (0..rng.gen_range(0..128))
+                .map(|_| rng.gen_range(0..alphabet))
+                .collect::<Vec<u8>>();
+
+            let length = rng.gen_range(1..80);
+            let mut pattern = Pattern::from_byte_vec(
+                (0..length).map(|_| rng.gen_range(0..alphabet)).collect()
+            );
+            for i in 0..length {
+                pattern.mask[i] = match rng.gen_range(0..4) {
+                    0 => 0x00,
+                    1 => rng.gen(),
+                    _ => 0xFF,
+                };
+                pattern.bytes[i] &= pattern.mask[i];
+            }
+
+            let capture_start = rng.gen_range(0..length);
+            let capture_end = rng.gen_range(capture_start..=length);
+            pattern.capture_groups.push(capture_start..capture_end);
+
+            for mode in [MatchMode::NonOverlapping, MatchMode::Overlapping] {
+                let expected = naive_scan_all(&bytes, &pattern, mode);
+                let result = scanner::simple::scan_all(&bytes, &pattern, mode);
+
+                assert_eq!(
+                    result.iter().map(|r| r.location).collect::<Vec<usize>>(),
+                    expected,
+                    "{mode:?} scan differs for pattern {pattern:?} in {bytes:?}",
+                );
+
+                for r in result.iter() {
+                    let capture = &r.captures[0];
+                    assert_eq!(capture.location, r.location + capture_start);
+                    assert_eq!(capture.bytes, bytes[capture.location..r.location + capture_end]);
+                }
+            }
+
+            assert_eq!(
+                scanner::simple::scan(&bytes, &pattern).map(|r| r.location),
+                naive_scan_all(&bytes, &pattern, MatchMode::NonOverlapping).first().copied(),
+            );
+        }
+    }
+
+    #[test]
+    fn simple_scanner_finds_patterns_with_jumps() {
+        let pattern = Pattern::from_byte_pattern("[E8] {0-4} C3 [??] {1-2} [90]").unwrap();
+        let bytes = [
+            0xE8, 0xAA, 0xAA, 0xC3, 0x11, 0x00, 0x90,
+            0xE8, 0xC3, 0x22, 0x00, 0x00, 0x90,
+            0xE8, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xC3, 0x33, 0x00, 0x90,
+        ];
+
+        let results = scanner::simple::scan_all(&bytes, &pattern, MatchMode::NonOverlapping);
+        assert_eq!(results.len(), 2);
+
+        assert_eq!(results[0].location, 0);
+        assert_eq!(results[0].captures[0].location, 0);
+        assert_eq!(results[0].captures[1].location, 4);
+        assert_eq!(results[0].captures[1].bytes, vec![0x11]);
+        assert_eq!(results[0].captures[2].location, 6);
+
+        assert_eq!(results[1].location, 7);
+        assert_eq!(results[1].captures[1].location, 9);
+        assert_eq!(results[1].captures[1].bytes, vec![0x22]);
+        assert_eq!(results[1].captures[2].location, 12);
+    }
+
+    #[test]
+    fn simple_scanner_does_not_report_overlapping_jump_matches() {
+        let pattern = Pattern::from_byte_pattern("01 {0-3} 01").unwrap();
+        let bytes = [0x01, 0x01, 0x01, 0x00, 0x00, 0x01];
+
+        let locations = |mode| scanner::simple::scan_all(&bytes, &pattern, mode)
+            .into_iter()
+            .map(|r| r.location)
+            .collect::<Vec<usize>>();
+
+        assert_eq!(locations(MatchMode::NonOverlapping), vec![0, 2]);
+        assert_eq!(locations(MatchMode::Overlapping), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn simple_scanner_honours_alternatives_and_nibble_wildcards() {
+        let pattern = Pattern::from_byte_pattern("(E8|E9) [?? ?? ?? ??] (48|4C) 8?").unwrap();
+        let bytes = [
+            0xE8, 0x01, 0x02, 0x03, 0x04, 0x48, 0x8B,
+            0xE9, 0x05, 0x06, 0x07, 0x08, 0x4C, 0x89,
+            0xEA, 0x05, 0x06, 0x07, 0x08, 0x4C, 0x89,
+            0xE9, 0x05, 0x06, 0x07, 0x08, 0x49, 0x89,
+            0xE9, 0x05, 0x06, 0x07, 0x08, 0x4C, 0x99,
+        ];
+
+        let results = scanner::simple::scan_all(&bytes, &pattern, MatchMode::NonOverlapping);
+        assert_eq!(results.iter().map(|r| r.location).collect::<Vec<usize>>(), vec![0, 7]);
+        assert_eq!(results[1].captures[0].bytes, vec![0x05, 0x06, 0x07, 0x08]);
+    }
+
+    #[test]
+    fn simple_scanner_can_scan_set() {
+        let randomness = include_bytes!("../../test/random.bin");
+        let patterns = [
+            ("fixed", Pattern::from_byte_slice(&[0x75, 0x84, 0x4A, 0xEF, 0x23, 0x24, 0xCA, 0x35])),
+            ("captures", Pattern::from_byte_pattern("B7 [?? CF D8 ??] 0A ?? 27").unwrap()),
+            ("frequent", Pattern::from_byte_pattern("09 02").unwrap()),
+            ("leading_wildcard", Pattern::from_byte_pattern("?? 09 ?? 02").unwrap()),
+            ("unanchored", Pattern::from_byte_pattern("m1111???? ?? m????1111 ?? ?? m0000????").unwrap()),
+            ("jump", Pattern::from_byte_pattern("09 {0-8} [02] {2} 27").unwrap()),
+            ("unanchored_jump", Pattern::from_byte_pattern("m0000???? {1-3} [09 02]").unwrap()),
+            ("alternatives", Pattern::from_byte_pattern("(09|0A) [0?] (02|?3)").unwrap()),
+            ("missing", Pattern::from_byte_slice(&[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF])),
+        ];
+        let set = PatternSet::new(patterns.clone());
+
+        for mode in [MatchMode::NonOverlapping, MatchMode::Overlapping] {
+            let results = scanner::simple::scan_set(randomness, &set, mode);
+
+            assert_eq!(results.len(), patterns.len());
+            for (name, pattern) in patterns.iter() {
+                assert_eq!(
+                    results[*name],
+                    scanner::simple::scan_all(randomness, pattern, mode),
+                    "{mode:?} results for {name} differ",
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn simple_scanner_checks_expectations() {
+        let bytes = [0x00, 0xAA, 0xAA, 0xAA, 0x01, 0xBB, 0x02, 0xBB];
+
+        let unique = Pattern::from_byte_pattern("01 [BB]").unwrap();
+        let result = scanner::simple::scan_unique(&bytes, &unique).unwrap();
+        assert_eq!(result.location, 4);
+        assert_eq!(result.captures[0].bytes, vec![0xBB]);
+
+        // Overlapping matches count as well
+        let overlapping = Pattern::from_byte_pattern("AA AA").unwrap();
+        assert_eq!(
+            scanner::simple::scan_unique(&bytes, &overlapping),
+            Err(ExpectationError::Ambiguous { count: 2, locations: vec![1, 2] }),
+        );
+        assert_eq!(
+            scanner::simple::scan_expecting(&bytes, &overlapping, ScanExpectation::Exactly(2)).unwrap().len(),
+            2,
+        );
+        assert_eq!(
+            scanner::simple::scan_expecting(&bytes, &unique, ScanExpectation::Exactly(2)),
+            Err(ExpectationError::TooFew { count: 1, locations: vec![4] }),
+        );
+
+        let missing = Pattern::from_byte_pattern("CC").unwrap();
+        assert_eq!(scanner::simple::scan_unique(&bytes, &missing), Err(ExpectationError::NotFound));
+        assert_eq!(scanner::simple::scan_expecting(&bytes, &missing, ScanExpectation::Exactly(0)), Ok(vec![]));
+    }
+
+    #[test]
+    fn simple_scanner_honours_alignment() {
+        let pattern = Pattern::from_byte_pattern("CC [CC]").unwrap();
+        let slice: &[u8] = &[0xCC; 20];
+
+        // The unaligned match at the start doesn't hide the aligned ones
+        let options = ScanOptions::aligned_at(1, 8);
+        let result = scanner::simple::scan_with_options(&slice[1..], &pattern, &options).unwrap();
+        assert_eq!(result.location, 7);
+        assert_eq!(result.captures[0].location, 8);
+
+        let locations = |mode, options| scanner::simple::scan_all_with_options(slice, &pattern, mode, &options)
+            .into_iter()
+            .map(|r| r.location)
+            .collect::<Vec<usize>>();
+
+        assert_eq!(locations(MatchMode::NonOverlapping, ScanOptions::aligned(4)), vec![0, 4, 8, 12, 16]);
+        assert_eq!(locations(MatchMode::NonOverlapping, ScanOptions::with_stride(3, 1)), vec![1, 4, 7, 10, 13, 16]);
+        assert_eq!(locations(MatchMode::Overlapping, ScanOptions::default()), (0..19).collect::<Vec<_>>());
+
+        // Matches only block the ones they overlap with, even with a stride of one byte
+        let long = Pattern::from_byte_pattern("CC CC CC").unwrap();
+        let results = scanner::simple::scan_all_with_options(
+            slice,
+            &long,
+            MatchMode::NonOverlapping,
+            &ScanOptions::aligned(2),
+        );
+        assert_eq!(results.iter().map(|r| r.location).collect::<Vec<_>>(), vec![0, 4, 8, 12, 16]);
+    }
+
+    #[test]
+    fn simple_scanner_scans_in_reverse() {
+        let randomness = include_bytes!("../../test/random.bin");
+        let pattern = Pattern::from_byte_pattern("09 [02]").unwrap();
+        let forward = scanner::simple::scan_all(randomness, &pattern, MatchMode::Overlapping);
+
+        let reverse = |from| scanner::simple::scan_reverse(randomness, &pattern, from);
+        let reverse_location = |from| reverse(from).map(|r| r.location);
+
+        // Walks back from a match, from right before it and from past the end of the bytes
+        assert_eq!(reverse(usize::MAX).as_ref(), forward.last());
+        assert_eq!(reverse(forward[20].location).as_ref(), Some(&forward[20]));
+        assert_eq!(reverse_location(forward[20].location - 1), Some(forward[19].location));
+        assert_eq!(reverse_location(forward[0].location - 1), None);
+
+        // Matches that extend past `from` count
+        let slice: &[u8] = &[0xAA, 0x01, 0x02, 0xAA];
+        let result = scanner::simple::scan_reverse(slice, &Pattern::from_byte_pattern("01 02").unwrap(), 1);
+        assert_eq!(result.map(|r| r.location), Some(1));
+
+        // Blocks further back keep to the stride
+        let slice: &[u8] = &[0xCC; 0x3000];
+        let pattern = Pattern::from_byte_pattern("CC CC").unwrap();
+        let options = ScanOptions::with_stride(0x1003, 2);
+        let reverse = |from| scanner::simple::scan_reverse_with_options(slice, &pattern, from, &options);
+        assert_eq!(reverse(0x2FFF).map(|r| r.location), Some(0x2008));
+        assert_eq!(reverse(0x2007).map(|r| r.location), Some(0x1005));
+        assert_eq!(reverse(0x1004).map(|r| r.location), Some(0x2));
+    }
+
+    #[test]
+    fn simple_scanner_scans_all_in_reverse() {
+        let pattern = Pattern::from_byte_pattern("AA ?? AA").unwrap();
+        let slice: &[u8] = &[0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA];
+
+        let locations = |mode| scanner::simple::scan_all_reverse(slice, &pattern, mode)
+            .into_iter()
+            .map(|r| r.location)
+            .collect::<Vec<usize>>();
+
+        // Non-overlapping matches are taken from the end
+        assert_eq!(locations(MatchMode::NonOverlapping), vec![3, 0]);
+        assert_eq!(locations(MatchMode::Overlapping), vec![3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn simple_scanner_matches_naive_scanner_with_strides() {
+        let mut rng = StdRng::seed_from_u64(0x737472696465);
+
+        for _ in 0..1000 {
+            let bytes = (0..rng.gen_range(0..128))
+                .map(|_| rng.gen_range(0..2u8))
+                .collect::<Vec<u8>>();
+            let pattern = Pattern::from_byte_vec((0..rng.gen_range(1..6)).map(|_| rng.gen_range(0..2u8)).collect());
+
+            let stride = rng.gen_range(1..9);
+            let options = ScanOptions::with_stride(stride, rng.gen_range(0..stride));
+
+            for mode in [MatchMode::NonOverlapping, MatchMode::Overlapping] {
+                let mut next_allowed = 0;
+                let expected = naive_scan_all(&bytes, &pattern, MatchMode::Overlapping)
+                    .into_iter()
+                    .filter(|offset| options.accepts(*offset))
+                    .filter(|offset| {
+                        let allowed = *offset >= next_allowed;
+                        if allowed && mode == MatchMode::NonOverlapping {
+                            next_allowed = offset + pattern.length;
+                        }
+
+                        allowed
+                    })
+                    .collect::<Vec<usize>>();
+
+                let result = scanner::simple::scan_all_with_options(&bytes, &pattern, mode, &options);
+                assert_eq!(
+                    result.iter().map(|r| r.location).collect::<Vec<usize>>(),
+                    expected,
+                    "{mode:?} scan with {options:?} differs for pattern {pattern:?} in {bytes:?}",
+                );
+            }
+        }
+    }
+
+    /// Reference implementation that does a masked compare of the whole pattern at every offset.
+    fn naive_scan_all(bytes: &[u8], pattern: &Pattern, mode: MatchMode) -> Vec<usize> {
+        let mut results = Vec::new();
+
+        let mut offset = 0;
+        while offset + pattern.length <= bytes.len() {
+            let matches = (0..pattern.length)
+                .all(|i| bytes[offset + i] & pattern.mask[i] == pattern.bytes[i]);
+
+            if matches {
+                results.push(offset);
+            }
+
+            offset += match (matches, mode) {
+                (true, MatchMode::NonOverlapping) => pattern.length,
+                _ => 1,
+            };
+        }
+
+        results
+    }
 }
@@ -0,0 +1,261 @@
+use std::io;
+use std::mem;
+
+use crate::pattern::Pattern;
+use crate::scanner::simple;
+use crate::scanner::{MatchMode, ScanResult};
+use crate::scanner::result::rebase_result;
+
+/// Size of the buffer used when scanning an `io::Read`.
+const READ_BUFFER_SIZE: usize = 0x10000;
+
+/// Scans data that is handed to it one chunk at a time. The last `pattern.max_length() - 1` bytes of
+/// every chunk are carried over to the next one so that matches straddling the edge between two
+/// chunks are still found. All reported locations are absolute, computed from the base that was
+/// passed in with each chunk.
+pub struct StreamingScanner<'p> {
+    pattern: &'p Pattern,
+    mode: MatchMode,
+    /// Tail end of the data that has been fed so far.
+    carry: Vec<u8>,
+    /// Absolute location of the first byte in `carry`.
+    carry_base: usize,
+    /// Matches that start before this location overlap with an earlier match.
+    next_allowed: usize,
+}
+
+impl<'p> StreamingScanner<'p> {
+    pub fn new(pattern: &'p Pattern, mode: MatchMode) -> Self {
+        Self {
+            pattern,
+            mode,
+            carry: Vec::with_capacity(pattern.max_length().saturating_sub(1)),
+            carry_base: 0,
+            next_allowed: 0,
+        }
+    }
+
+    /// Scans the next chunk which is located at `base` and returns the matches that ended in it.
+    /// Chunks must be fed in ascending order. If the chunk doesn't directly follow the previous
+    /// one, no match is assumed to cross the gap between them.
+    ///
+    /// Patterns with jumps only have their matches reported once every byte their jumps could
+    /// reach has been fed, so the jump lengths come out the same as when scanning everything at
+    /// once. Call `finish` after the last chunk to get the matches that are still held back.
+    pub fn feed(&mut self, base: usize, chunk: &[u8]) -> Vec<ScanResult> {
+        let overlap = self.pattern.max_length().saturating_sub(1);
+        let mut results = Vec::new();
+
+        // Drop the carry if there's a gap, like with an unreadable page in between
+        if self.carry_base + self.carry.len() != base {
+            results = self.finish();
+            self.carry_base = base;
+        }
+
+        // Matches that start in the carry-over have to end in this chunk as the carry-over is
+        // shorter than the pattern. So we only need enough of the chunk to complete those.
+        let mut boundary = mem::take(&mut self.carry);
+        boundary.extend_from_slice(&chunk[..overlap.min(chunk.len())]);
+
+        let carry_length = boundary.len() - overlap.min(chunk.len());
+        let boundary_length = boundary.len();
+        results.extend(
+            self.scan_buffer(&boundary, self.carry_base, |location| {
+                location < carry_length && location + overlap < boundary_length
+            })
+        );
+
+        // Whatever doesn't fit in the chunk starts in the next carry-over
+        results.extend(
+            self.scan_buffer(chunk, base, |location| location + overlap < chunk.len())
+        );
+
+        // Keep the tail end of everything we've seen around for the next chunk
+        boundary.truncate(carry_length);
+        boundary.extend_from_slice(chunk);
+        let dropped = boundary.len().saturating_sub(overlap);
+        self.carry = boundary.split_off(dropped);
+        self.carry_base += dropped;
+
+        results
+    }
+
+    /// Reports the matches in the carry-over that were held back waiting for more bytes, which
+    /// only happens for patterns with jumps. The scanner can be fed again afterwards.
+    pub fn finish(&mut self) -> Vec<ScanResult> {
+        let carry = mem::take(&mut self.carry);
+        let results = self.scan_buffer(&carry, self.carry_base, |_| true);
+        self.carry_base += carry.len();
+
+        results
+    }
+
+    /// Scans `bytes` located at `base` for the matches that start at a location accepted by
+    /// `report`, applying the match mode across everything reported so far.
+    fn scan_buffer(
+        &mut self,
+        bytes: &[u8],
+        base: usize,
+        report: impl Fn(usize) -> bool,
+    ) -> Vec<ScanResult> {
+        let mut results = simple::scan_all(bytes, self.pattern, MatchMode::Overlapping);
+        results.retain(|r| report(r.location));
+
+        if self.mode == MatchMode::NonOverlapping {
+            results.retain(|r| {
+                if base + r.location < self.next_allowed {
+                    return false;
+                }
+
+                self.next_allowed = base + r.location + self.pattern.match_length(&bytes[r.location..]);
+                true
+            });
+        }
+
+        results.into_iter()
+            .map(|r| rebase_result(r, base))
+            .collect()
+    }
+}
+
+/// Scans every chunk in order, where each chunk is a base location paired with its bytes.
+pub fn scan_chunks<'a>(
+    chunks: impl IntoIterator<Item = (usize, &'a [u8])>,
+    pattern: &Pattern,
+    mode: MatchMode,
+) -> Vec<ScanResult> {
+    let mut scanner = StreamingScanner::new(pattern, mode);
+
+    let mut results = chunks.into_iter()
+        .flat_map(|(base, chunk)| scanner.feed(base, chunk))
+        .collect::<Vec<ScanResult>>();
+
+    results.append(&mut scanner.finish());
+    results
+}
+
+/// Reads `reader` until it's exhausted and scans everything that was read. Locations are relative
+/// to where the reader was at when this was called.
+pub fn scan_reader(
+    mut reader: impl io::Read,
+    pattern: &Pattern,
+    mode: MatchMode,
+) -> io::Result<Vec<ScanResult>> {
+    let mut scanner = StreamingScanner::new(pattern, mode);
+    let mut buffer = vec![0u8; READ_BUFFER_SIZE];
+    let mut base = 0;
+    let mut results = Vec::new();
+
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        results.append(&mut scanner.feed(base, &buffer[..read]));
+        base += read;
+    }
+
+    results.append(&mut scanner.finish());
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use crate::scanner;
+    use crate::scanner::MatchMode;
+    use crate::pattern::Pattern;
+    use crate::scanner::streaming::StreamingScanner;
+
+    #[test]
+    fn streaming_scanner_matches_simple_scanner() {
+        let randomness = include_bytes!("../../test/random.bin");
+        let patterns = [
+            Pattern::from_byte_pattern("B7 [?? CF D8 ??] 0A ?? 27").unwrap(),
+            Pattern::from_byte_pattern("09 02").unwrap(),
+            Pattern::from_byte_pattern("[09] ?? ?? ?? ?? ?? ?? ?? [02]").unwrap(),
+            Pattern::from_byte_pattern("[m0000????] m????0000 [??]").unwrap(),
+            Pattern::from_byte_pattern("09 {0-6} [02]").unwrap(),
+        ];
+
+        for pattern in patterns.iter() {
+            for chunk_size in [1, 3, 4096, 0x12345] {
+                // Keep the amount of chunks reasonable for the tiny chunk sizes
+                let bytes = if chunk_size < 4096 {
+                    &randomness[866000..868000]
+                } else {
+                    &randomness[..]
+                };
+
+                for mode in [MatchMode::NonOverlapping, MatchMode::Overlapping] {
+                    let chunks = bytes.chunks(chunk_size)
+                        .enumerate()
+                        .map(|(i, c)| (i * chunk_size, c));
+
+                    assert_eq!(
+                        scanner::streaming::scan_chunks(chunks, pattern, mode),
+                        scanner::simple::scan_all(bytes, pattern, mode),
+                        "{mode:?} scan with chunks of {chunk_size} differs for {pattern:?}",
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn streaming_scanner_reports_captures_across_chunk_edges() {
+        let pattern = Pattern::from_byte_pattern("01 [02 03] 04").unwrap();
+        let mut scanner = StreamingScanner::new(&pattern, MatchMode::NonOverlapping);
+
+        assert_eq!(scanner.feed(0x1000, &[0x00, 0x01, 0x02]), vec![]);
+
+        let results = scanner.feed(0x1003, &[0x03, 0x04, 0x05]);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].location, 0x1001);
+        assert_eq!(results[0].captures[0].location, 0x1002);
+        assert_eq!(results[0].captures[0].bytes, vec![0x02, 0x03]);
+    }
+
+    #[test]
+    fn streaming_scanner_does_not_match_across_gaps() {
+        let pattern = Pattern::from_byte_pattern("01 02").unwrap();
+        let mut scanner = StreamingScanner::new(&pattern, MatchMode::NonOverlapping);
+
+        assert_eq!(scanner.feed(0x1000, &[0x00, 0x01]), vec![]);
+        assert_eq!(scanner.feed(0x3000, &[0x02, 0x00]), vec![]);
+    }
+
+    #[test]
+    fn streaming_scanner_holds_back_jump_matches_until_finished() {
+        let pattern = Pattern::from_byte_pattern("01 {0-4} [02]").unwrap();
+        let mut scanner = StreamingScanner::new(&pattern, MatchMode::NonOverlapping);
+
+        // The jump could still reach into the next chunk, so nothing is reported yet
+        assert_eq!(scanner.feed(0x1000, &[0x01, 0x02]), vec![]);
+
+        let results = scanner.finish();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].location, 0x1000);
+        assert_eq!(results[0].captures[0].location, 0x1001);
+    }
+
+    #[test]
+    fn streaming_scanner_can_scan_readers() {
+        let randomness = include_bytes!("../../test/random.bin");
+        let pattern = Pattern::from_byte_pattern("B7 [?? CF D8 ??] 0A ?? 27").unwrap();
+        let results = scanner::streaming::scan_reader(
+            io::Cursor::new(randomness),
+            &pattern,
+            MatchMode::NonOverlapping,
+        ).unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].location, 867776);
+        assert_eq!(results[0].captures[0].location, 867777);
+        assert_eq!(results[0].captures[0].bytes, vec![0xc6, 0xcf, 0xd8, 0x11]);
+    }
+}
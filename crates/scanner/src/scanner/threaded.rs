@@ -1,124 +1,418 @@
-use std::thread;
+use std::fmt;
+use std::error::Error;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-use crate::pattern::Pattern;
+use crate::pattern::{Pattern, PatternSet};
 use crate::scanner::simple;
-use crate::scanner::ScanResult;
-use crate::scanner::result::rebase_result;
-
-fn split_into_chunks(chunks: usize, bytes: &'static [u8], overlap: usize) -> Vec<(usize, &'static [u8])> {
-    let bytes_per_chunk = bytes.len() / chunks;
-
-    let mut offset: usize = 0;
-    let mut results = Vec::new();
-
-    for _ in 0..chunks {
-        let start = offset;
-        // Clamp the end to the range so we don't go out-of-bounds
-        let end = clamp(
-            start + (bytes_per_chunk + overlap),
-            0,
-            bytes.len()
-        );
+use crate::scanner::pool::{default_parallelism, ScanPool};
+use crate::scanner::{ExpectationError, MatchMode, ScanExpectation, ScanOptions, ScanResult};
+use crate::scanner::result::{build_result, rebase_result};
+
+/// Amount of bytes a thread scans before it checks if another thread found an earlier match.
+const CANCELLATION_BLOCK_SIZE: usize = 0x40000;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ThreadedScanError {
+    /// The work was to be split over zero threads.
+    ZeroParallelism,
+    /// The pattern doesn't contain any bytes to match.
+    EmptyPattern,
+    /// The pattern didn't match as often as expected.
+    Expectation(ExpectationError),
+}
 
-        results.push((offset, &bytes[start..end]));
-        offset += bytes_per_chunk;
+impl fmt::Display for ThreadedScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ZeroParallelism => write!(f, "cannot split a scan over zero threads"),
+            Self::EmptyPattern => write!(f, "pattern does not contain any bytes to match"),
+            Self::Expectation(e) => write!(f, "{e}"),
+        }
     }
+}
 
-    results
+impl Error for ThreadedScanError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Expectation(e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
-pub fn scan(bytes: &'static [u8], pattern: &Pattern, parallelism: Option<usize>) -> Option<ScanResult> {
-    let parallelism = parallelism.unwrap_or(default_parallelism());
-    let chunks = split_into_chunks(parallelism, bytes, pattern.length - 1);
+type Task<'s, T> = Box<dyn FnOnce() -> T + Send + 's>;
 
-    let mut handles = Vec::new();
-    for (offset, chunk) in chunks.into_iter() {
-        let pattern = pattern.clone();
-        let handle = thread::spawn(move || {
-            simple::scan(chunk, &pattern)
-        });
+/// Part of the bytes handed to a single thread.
+struct Chunk<'a> {
+    /// Location of the chunk in the scanned bytes.
+    offset: usize,
+    /// Matches starting at or after this location belong to the next chunk.
+    owned_end: usize,
+    /// The bytes from `offset` up to `owned_end`, plus the overlap with the next chunk.
+    bytes: &'a [u8],
+}
 
-        handles.push((offset, handle));
+/// Splits the bytes into chunks that start at a multiple of `alignment`, so that offsets in a chunk
+/// have the same remainder as the offsets in the bytes.
+fn split_into_chunks(chunks: usize, bytes: &[u8], overlap: usize, alignment: usize) -> Vec<Chunk<'_>> {
+    if bytes.is_empty() {
+        return vec![];
     }
 
-    for handle in handles {
-        // Rebase the scan result to its respective chunk
-        let result = handle.1.join()
-            .unwrap()
-            .map(|r| rebase_result(r, handle.0));
+    // Don't hand out empty chunks when there are fewer bytes than chunks
+    let chunks = chunks.clamp(1, bytes.len());
+    let bytes_per_chunk = bytes.len().div_ceil(chunks).next_multiple_of(alignment);
 
-        if result.is_some() {
-            return result;
-        }
-    }
+    (0..bytes.len())
+        .step_by(bytes_per_chunk)
+        .map(|offset| {
+            let owned_end = (offset + bytes_per_chunk).min(bytes.len());
+            // Clamp the end to the range so we don't go out-of-bounds
+            let end = (owned_end + overlap).min(bytes.len());
 
-    None
+            Chunk { offset, owned_end, bytes: &bytes[offset..end] }
+        })
+        .collect()
 }
 
-pub fn scan_all(bytes: &'static [u8], pattern: &Pattern, parallelism: Option<usize>) -> Vec<ScanResult> {
-    let parallelism = parallelism.unwrap_or(default_parallelism());
-    let chunks = split_into_chunks(parallelism, bytes, pattern.length - 1);
+/// Scans for the first occurrence of `pattern` by splitting `bytes` into `parallelism` chunks that
+/// are scanned on the global scan pool.
+pub fn scan(
+    bytes: &[u8],
+    pattern: &Pattern,
+    parallelism: Option<usize>,
+) -> Result<Option<ScanResult>, ThreadedScanError> {
+    scan_with_options(bytes, pattern, &ScanOptions::default(), parallelism)
+}
 
-    let mut handles = Vec::new();
-    for (offset, chunk) in chunks.into_iter() {
-        let pattern = pattern.clone();
-        let handle = thread::spawn(move || {
-            simple::scan_all(chunk, &pattern)
-        });
+/// Scans for the first match that starts at an offset the options accept, see
+/// `simple::scan_with_options`.
+pub fn scan_with_options(
+    bytes: &[u8],
+    pattern: &Pattern,
+    options: &ScanOptions,
+    parallelism: Option<usize>,
+) -> Result<Option<ScanResult>, ThreadedScanError> {
+    ScanPool::global().scan_chunked(bytes, pattern, options, chunk_count(parallelism)?)
+}
+
+pub fn scan_all(
+    bytes: &[u8],
+    pattern: &Pattern,
+    mode: MatchMode,
+    parallelism: Option<usize>,
+) -> Result<Vec<ScanResult>, ThreadedScanError> {
+    scan_all_with_options(bytes, pattern, mode, &ScanOptions::default(), parallelism)
+}
+
+/// Scans for all matches that start at an offset the options accept, see
+/// `simple::scan_all_with_options`.
+pub fn scan_all_with_options(
+    bytes: &[u8],
+    pattern: &Pattern,
+    mode: MatchMode,
+    options: &ScanOptions,
+    parallelism: Option<usize>,
+) -> Result<Vec<ScanResult>, ThreadedScanError> {
+    ScanPool::global().scan_all_chunked(bytes, pattern, mode, options, chunk_count(parallelism)?)
+}
+
+/// Scans for a pattern that should match exactly once, see `simple::scan_unique`.
+pub fn scan_unique(
+    bytes: &[u8],
+    pattern: &Pattern,
+    parallelism: Option<usize>,
+) -> Result<ScanResult, ThreadedScanError> {
+    scan_expecting(bytes, pattern, ScanExpectation::Unique, parallelism)
+        .map(|mut results| results.remove(0))
+}
 
-        handles.push((offset, handle));
+/// Scans for all matches and checks that there's as many as expected, see `simple::scan_expecting`.
+pub fn scan_expecting(
+    bytes: &[u8],
+    pattern: &Pattern,
+    expectation: ScanExpectation,
+    parallelism: Option<usize>,
+) -> Result<Vec<ScanResult>, ThreadedScanError> {
+    let results = scan_all(bytes, pattern, MatchMode::Overlapping, parallelism)?;
+
+    expectation.check(results)
+        .map_err(ThreadedScanError::Expectation)
+}
+
+/// Scans for all patterns in the set, every thread does a single pass over its chunk.
+pub fn scan_set(
+    bytes: &[u8],
+    set: &PatternSet,
+    mode: MatchMode,
+    parallelism: Option<usize>,
+) -> Result<HashMap<String, Vec<ScanResult>>, ThreadedScanError> {
+    ScanPool::global().scan_set_chunked(bytes, set, mode, chunk_count(parallelism)?)
+}
+
+impl ScanPool {
+    /// Scans for the first occurrence of `pattern` using a chunk for every thread in the pool.
+    pub fn scan(&self, bytes: &[u8], pattern: &Pattern) -> Result<Option<ScanResult>, ThreadedScanError> {
+        self.scan_with_options(bytes, pattern, &ScanOptions::default())
+    }
+
+    pub fn scan_with_options(
+        &self,
+        bytes: &[u8],
+        pattern: &Pattern,
+        options: &ScanOptions,
+    ) -> Result<Option<ScanResult>, ThreadedScanError> {
+        self.scan_chunked(bytes, pattern, options, self.threads())
+    }
+
+    pub fn scan_all(
+        &self,
+        bytes: &[u8],
+        pattern: &Pattern,
+        mode: MatchMode,
+    ) -> Result<Vec<ScanResult>, ThreadedScanError> {
+        self.scan_all_with_options(bytes, pattern, mode, &ScanOptions::default())
+    }
+
+    pub fn scan_all_with_options(
+        &self,
+        bytes: &[u8],
+        pattern: &Pattern,
+        mode: MatchMode,
+        options: &ScanOptions,
+    ) -> Result<Vec<ScanResult>, ThreadedScanError> {
+        self.scan_all_chunked(bytes, pattern, mode, options, self.threads())
+    }
+
+    pub fn scan_unique(&self, bytes: &[u8], pattern: &Pattern) -> Result<ScanResult, ThreadedScanError> {
+        self.scan_expecting(bytes, pattern, ScanExpectation::Unique)
+            .map(|mut results| results.remove(0))
     }
 
-    let mut results = Vec::new();
-    for handle in handles {
-        // Rebase the scan result to its respective chunk
-        results.append(
-            &mut handle.1.join()
-                .unwrap()
+    pub fn scan_expecting(
+        &self,
+        bytes: &[u8],
+        pattern: &Pattern,
+        expectation: ScanExpectation,
+    ) -> Result<Vec<ScanResult>, ThreadedScanError> {
+        let results = self.scan_all(bytes, pattern, MatchMode::Overlapping)?;
+
+        expectation.check(results)
+            .map_err(ThreadedScanError::Expectation)
+    }
+
+    pub fn scan_set(
+        &self,
+        bytes: &[u8],
+        set: &PatternSet,
+        mode: MatchMode,
+    ) -> Result<HashMap<String, Vec<ScanResult>>, ThreadedScanError> {
+        self.scan_set_chunked(bytes, set, mode, self.threads())
+    }
+
+    fn scan_chunked(
+        &self,
+        bytes: &[u8],
+        pattern: &Pattern,
+        options: &ScanOptions,
+        chunks: usize,
+    ) -> Result<Option<ScanResult>, ThreadedScanError> {
+        if pattern.length == 0 {
+            return Err(ThreadedScanError::EmptyPattern);
+        }
+
+        // Location of the earliest match any of the threads found so far
+        let earliest = AtomicUsize::new(usize::MAX);
+        let earliest = &earliest;
+
+        let tasks = split_into_chunks(chunks, bytes, pattern.max_length() - 1, options.stride())
+            .into_iter()
+            .map(|chunk| {
+                Box::new(move || scan_until_cancelled(chunk, pattern, options, earliest)) as Task<Option<ScanResult>>
+            })
+            .collect();
+
+        Ok(
+            self.run(tasks)
                 .into_iter()
-                .map(|r| rebase_result(r, handle.0))
-                .collect::<Vec<ScanResult>>()
-        );
+                .flatten()
+                .min_by_key(|r| r.location)
+        )
+    }
+
+    fn scan_all_chunked(
+        &self,
+        bytes: &[u8],
+        pattern: &Pattern,
+        mode: MatchMode,
+        options: &ScanOptions,
+        chunks: usize,
+    ) -> Result<Vec<ScanResult>, ThreadedScanError> {
+        if pattern.length == 0 {
+            return Err(ThreadedScanError::EmptyPattern);
+        }
+
+        let tasks = split_into_chunks(chunks, bytes, pattern.max_length() - 1, options.stride())
+            .into_iter()
+            .map(|chunk| Box::new(move || {
+                // Chunks can't know where matches in the chunk before them ended, so they report
+                // every match and overlapping ones are dropped once everything is merged.
+                simple::scan_all_with_options(chunk.bytes, pattern, MatchMode::Overlapping, options)
+                    .into_iter()
+                    // Rebase the scan result to its respective chunk
+                    .map(|r| rebase_result(r, chunk.offset))
+                    .filter(|r| r.location < chunk.owned_end)
+                    .collect::<Vec<ScanResult>>()
+            }) as Task<Vec<ScanResult>>)
+            .collect();
+
+        let mut results = self.run(tasks)
+            .into_iter()
+            .flatten()
+            .collect::<Vec<ScanResult>>();
+
+        results.sort_by_key(|r| r.location);
+        results.dedup_by_key(|r| r.location);
+
+        if mode == MatchMode::NonOverlapping {
+            remove_overlapping(&mut results, bytes, pattern);
+        }
+
+        Ok(results)
     }
 
-    results
+    fn scan_set_chunked(
+        &self,
+        bytes: &[u8],
+        set: &PatternSet,
+        mode: MatchMode,
+        chunks: usize,
+    ) -> Result<HashMap<String, Vec<ScanResult>>, ThreadedScanError> {
+        // The chunk overlap is sized for the longest pattern, so shorter patterns can match
+        // entirely inside of the overlap. Only the chunk a match starts in gets to report it.
+        let tasks = split_into_chunks(chunks, bytes, set.max_length().saturating_sub(1), 1)
+            .into_iter()
+            .map(|chunk| Box::new(move || {
+                let mut matches = Vec::new();
+                set.for_each_match(chunk.bytes, |index, location| {
+                    if chunk.offset + location < chunk.owned_end {
+                        matches.push((index, chunk.offset + location));
+                    }
+                });
+
+                matches
+            }) as Task<Vec<(usize, usize)>>)
+            .collect();
+
+        let mut matches = vec![Vec::new(); set.len()];
+        for (index, location) in self.run(tasks).into_iter().flatten() {
+            matches[index].push(location);
+        }
+
+        Ok(
+            matches.into_iter()
+                .enumerate()
+                .map(|(index, mut locations)| {
+                    let (name, pattern) = set.entry(index);
+
+                    locations.sort_unstable();
+                    locations.dedup();
+
+                    let mut results = locations.into_iter()
+                        .map(|location| build_result(bytes, pattern, location))
+                        .collect::<Vec<ScanResult>>();
+
+                    if mode == MatchMode::NonOverlapping {
+                        remove_overlapping(&mut results, bytes, pattern);
+                    }
+
+                    (name.to_string(), results)
+                })
+                .collect()
+        )
+    }
 }
 
-fn default_parallelism() -> usize {
-    thread::available_parallelism().unwrap().get()
+/// Scans the chunk in blocks and stops as soon as another thread found a match that starts before
+/// the block it's about to scan, as nothing this thread can still find would be earlier.
+fn scan_until_cancelled(
+    chunk: Chunk,
+    pattern: &Pattern,
+    options: &ScanOptions,
+    earliest: &AtomicUsize,
+) -> Option<ScanResult> {
+    let overlap = pattern.max_length() - 1;
+    let owned_length = chunk.owned_end - chunk.offset;
+    // Blocks start at a multiple of the stride as well, same as the chunks
+    let block_size = CANCELLATION_BLOCK_SIZE.next_multiple_of(options.stride());
+
+    let mut block_start = 0;
+    while block_start < owned_length {
+        if earliest.load(Ordering::Relaxed) < chunk.offset + block_start {
+            return None;
+        }
+
+        let block_end = (block_start + block_size).min(owned_length);
+        let block = &chunk.bytes[block_start..(block_end + overlap).min(chunk.bytes.len())];
+
+        if let Some(result) = simple::scan_with_options(block, pattern, options) {
+            let result = rebase_result(result, chunk.offset + block_start);
+            earliest.fetch_min(result.location, Ordering::Relaxed);
+            return Some(result);
+        }
+
+        block_start = block_end;
+    }
+
+    None
+}
+
+/// Drops every match that starts before the end of the last match that was kept.
+fn remove_overlapping(results: &mut Vec<ScanResult>, bytes: &[u8], pattern: &Pattern) {
+    let mut next_allowed = 0;
+    results.retain(|r| {
+        if r.location < next_allowed {
+            return false;
+        }
+
+        next_allowed = r.location + pattern.match_length(&bytes[r.location..]);
+        true
+    });
 }
 
-fn clamp(input: usize, min: usize, max: usize) -> usize {
-    if input < min {
-        min
-    } else if input > max {
-        max
-    } else {
-        input
+fn chunk_count(parallelism: Option<usize>) -> Result<usize, ThreadedScanError> {
+    match parallelism {
+        Some(0) => Err(ThreadedScanError::ZeroParallelism),
+        Some(parallelism) => Ok(parallelism),
+        None => Ok(default_parallelism()),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::scanner;
-    use crate::pattern::Pattern;
+    use crate::scanner::{ExpectationError, MatchMode, ScanExpectation, ScanOptions};
+    use crate::scanner::pool::ScanPool;
+    use crate::scanner::threaded::ThreadedScanError;
+    use crate::pattern::{Pattern, PatternSet};
 
     #[test]
     fn threaded_scanner_behaves_with_empty_slice() {
         let pattern = Pattern::from_byte_slice(&[0xAA, 0xAA, 0xAA, 0xAA, 0xAA]);
-        let slice = Box::leak(Box::new([]));
+        let slice: &[u8] = &[];
         let result = scanner::threaded::scan(slice, &pattern, Some(4));
 
-        assert_eq!(result, None);
+        assert_eq!(result, Ok(None));
     }
 
     #[test]
     fn threaded_scanner_behaves_with_too_long_of_a_pattern() {
         let pattern = Pattern::from_byte_slice(&[0xAA, 0xAA, 0xAA, 0xAA, 0xAA]);
-        let slice = Box::leak(Box::new([0x00, 0x00, 0x00, 0x00]));
+        let slice: &[u8] = &[0x00, 0x00, 0x00, 0x00];
         let result = scanner::threaded::scan(slice, &pattern, Some(4));
 
-        assert_eq!(result, None);
+        assert_eq!(result, Ok(None));
     }
 
     #[test]
@@ -126,6 +420,7 @@ mod tests {
         let pattern = Pattern::from_byte_slice(&[0x75, 0x84, 0x4A, 0xEF, 0x23, 0x24, 0xCA, 0x35]);
         let randomness = include_bytes!("../../test/random.bin");
         let result = scanner::threaded::scan(randomness, &pattern, Some(4))
+            .unwrap()
             .unwrap();
 
         assert_eq!(result.location, 1309924);
@@ -137,6 +432,7 @@ mod tests {
         let pattern = Pattern::from_byte_pattern("B7 [?? CF D8 ??] 0A ?? 27").unwrap();
         let randomness = include_bytes!("../../test/random.bin");
         let result = scanner::threaded::scan(randomness, &pattern, Some(4))
+            .unwrap()
             .unwrap();
 
         assert_eq!(result.location, 867776);
@@ -151,15 +447,222 @@ mod tests {
         let randomness = include_bytes!("../../test/random.bin");
         let result = scanner::threaded::scan(randomness, &pattern, Some(4));
 
-        assert_eq!(result, None);
+        assert_eq!(result, Ok(None));
     }
 
     #[test]
     fn threaded_scanner_can_scan_all() {
         let pattern = Pattern::from_byte_slice(&[0x09, 0x02]);
         let randomness = include_bytes!("../../test/random.bin");
-        let result = scanner::threaded::scan_all(randomness, &pattern, Some(4));
+        let result = scanner::threaded::scan_all(randomness, &pattern, MatchMode::NonOverlapping, Some(4))
+            .unwrap();
 
         assert_eq!(result.len(), 35);
     }
+
+    #[test]
+    fn threaded_scanner_scan_all_matches_simple_scanner() {
+        let randomness = include_bytes!("../../test/random.bin");
+        let patterns = [
+            Pattern::from_byte_pattern("09 ?? 02").unwrap(),
+            Pattern::from_byte_pattern("m0000???? [m????0000]").unwrap(),
+            Pattern::from_byte_pattern("09 {0-12} [02] {1-3} ??").unwrap(),
+            Pattern::from_byte_pattern("(09|0A) [?2] (27|3?)").unwrap(),
+        ];
+
+        for pattern in patterns.iter() {
+            for mode in [MatchMode::NonOverlapping, MatchMode::Overlapping] {
+                assert_eq!(
+                    scanner::threaded::scan_all(randomness, pattern, mode, Some(7)).unwrap(),
+                    scanner::simple::scan_all(randomness, pattern, mode),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn threaded_scanner_scan_set_matches_simple_scanner() {
+        let randomness = include_bytes!("../../test/random.bin");
+        let set = PatternSet::new([
+            ("fixed", Pattern::from_byte_slice(&[0x75, 0x84, 0x4A, 0xEF, 0x23, 0x24, 0xCA, 0x35])),
+            ("captures", Pattern::from_byte_pattern("B7 [?? CF D8 ??] 0A ?? 27").unwrap()),
+            ("short", Pattern::from_byte_pattern("09 02").unwrap()),
+            ("long", Pattern::from_byte_pattern("09 ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? 02").unwrap()),
+            ("jump", Pattern::from_byte_pattern("09 {0-20} [02]").unwrap()),
+        ]);
+
+        for mode in [MatchMode::NonOverlapping, MatchMode::Overlapping] {
+            assert_eq!(
+                scanner::threaded::scan_set(randomness, &set, mode, Some(5)).unwrap(),
+                scanner::simple::scan_set(randomness, &set, mode),
+            );
+        }
+    }
+
+    #[test]
+    fn threaded_scanner_finds_patterns_with_jumps() {
+        let randomness = include_bytes!("../../test/random.bin");
+        let pattern = Pattern::from_byte_pattern("B7 [??] {1-6} D8 [??] 0A {1} 27").unwrap();
+        let result = scanner::threaded::scan(randomness, &pattern, Some(4))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(result.location, 867776);
+        assert_eq!(result.captures[0].location, 867777);
+        assert_eq!(result.captures[0].bytes, vec![0xc6]);
+        assert_eq!(result.captures[1].location, 867780);
+        assert_eq!(result.captures[1].bytes, vec![0x11]);
+    }
+
+    #[test]
+    fn threaded_scanner_scans_owned_buffers() {
+        let buffer = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/test/random.bin")).unwrap();
+        let pattern = Pattern::from_byte_pattern("B7 [?? CF D8 ??] 0A ?? 27").unwrap();
+        let result = scanner::threaded::scan(&buffer, &pattern, Some(4))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(result.location, 867776);
+        assert_eq!(result.captures[0].bytes, vec![0xc6, 0xcf, 0xd8, 0x11]);
+    }
+
+    #[test]
+    fn threaded_scanner_returns_errors_for_degenerate_input() {
+        let randomness = include_bytes!("../../test/random.bin");
+        let pattern = Pattern::from_byte_slice(&[0x09, 0x02]);
+        let empty = Pattern::from_byte_slice(&[]);
+
+        assert_eq!(
+            scanner::threaded::scan(randomness, &pattern, Some(0)),
+            Err(ThreadedScanError::ZeroParallelism),
+        );
+        assert_eq!(
+            scanner::threaded::scan_all(randomness, &pattern, MatchMode::NonOverlapping, Some(0)),
+            Err(ThreadedScanError::ZeroParallelism),
+        );
+        assert_eq!(
+            scanner::threaded::scan(randomness, &empty, Some(4)),
+            Err(ThreadedScanError::EmptyPattern),
+        );
+        assert_eq!(
+            scanner::threaded::scan_all(randomness, &empty, MatchMode::NonOverlapping, Some(4)),
+            Err(ThreadedScanError::EmptyPattern),
+        );
+    }
+
+    #[test]
+    fn threaded_scanner_checks_expectations() {
+        let randomness = include_bytes!("../../test/random.bin");
+        let pattern = Pattern::from_byte_slice(&randomness[0x1234..0x1244]);
+        let common = Pattern::from_byte_slice(&[0x09]);
+
+        let result = scanner::threaded::scan_unique(randomness, &pattern, Some(4)).unwrap();
+        assert_eq!(result.location, 0x1234);
+
+        let count = scanner::simple::scan_all(randomness, &common, MatchMode::Overlapping).len();
+        match scanner::threaded::scan_unique(randomness, &common, Some(4)) {
+            Err(ThreadedScanError::Expectation(ExpectationError::Ambiguous { count: c, locations })) => {
+                assert_eq!(c, count);
+                assert_eq!(locations.len(), count);
+            },
+            other => panic!("expected an ambiguous scan, got {other:?}"),
+        }
+
+        let pool = ScanPool::new(3).unwrap();
+        assert_eq!(pool.scan_expecting(randomness, &common, ScanExpectation::Exactly(count)).unwrap().len(), count);
+        assert_eq!(
+            scanner::threaded::scan_unique(randomness, &common, Some(0)),
+            Err(ThreadedScanError::ZeroParallelism),
+        );
+    }
+
+    #[test]
+    fn threaded_scanner_handles_slices_shorter_than_the_thread_count() {
+        let pattern = Pattern::from_byte_pattern("01 [02]").unwrap();
+        let slice: &[u8] = &[0x01, 0x02, 0x01, 0x02];
+
+        let result = scanner::threaded::scan(slice, &pattern, Some(16))
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.location, 0);
+        assert_eq!(result.captures[0].location, 1);
+
+        let results = scanner::threaded::scan_all(slice, &pattern, MatchMode::Overlapping, Some(16))
+            .unwrap();
+        assert_eq!(results.iter().map(|r| r.location).collect::<Vec<_>>(), vec![0, 2]);
+    }
+
+    #[test]
+    fn threaded_scanner_returns_the_earliest_match() {
+        let randomness = include_bytes!("../../test/random.bin");
+        let pattern = Pattern::from_byte_pattern("09 02").unwrap();
+
+        // The pattern occurs in pretty much every chunk so every thread finds something
+        for parallelism in [2, 3, 8, 33] {
+            assert_eq!(
+                scanner::threaded::scan(randomness, &pattern, Some(parallelism)).unwrap(),
+                scanner::simple::scan(randomness, &pattern),
+            );
+        }
+    }
+
+    #[test]
+    fn threaded_scanner_honours_alignment() {
+        let randomness = include_bytes!("../../test/random.bin");
+        let pattern = Pattern::from_byte_pattern("09 [??]").unwrap();
+
+        // Chunk sizes that aren't a multiple of the stride have to be rounded up to one
+        for options in [ScanOptions::aligned(16), ScanOptions::with_stride(3, 2), ScanOptions::with_stride(7, 5)] {
+            for parallelism in [1, 3, 8] {
+                assert_eq!(
+                    scanner::threaded::scan_with_options(randomness, &pattern, &options, Some(parallelism)).unwrap(),
+                    scanner::simple::scan_with_options(randomness, &pattern, &options),
+                );
+
+                for mode in [MatchMode::NonOverlapping, MatchMode::Overlapping] {
+                    let results = scanner::threaded::scan_all_with_options(
+                        randomness,
+                        &pattern,
+                        mode,
+                        &options,
+                        Some(parallelism),
+                    );
+                    let expected = scanner::simple::scan_all_with_options(randomness, &pattern, mode, &options);
+                    assert_eq!(results.unwrap(), expected);
+                }
+            }
+        }
+
+        let slice: &[u8] = &[0xAA; 100];
+        let pattern = Pattern::from_byte_pattern("AA").unwrap();
+        let results = ScanPool::new(7).unwrap()
+            .scan_all_with_options(slice, &pattern, MatchMode::Overlapping, &ScanOptions::aligned(8))
+            .unwrap();
+        assert_eq!(results.iter().map(|r| r.location).collect::<Vec<_>>(), (0..100).step_by(8).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn threaded_scanner_results_are_sorted_and_unique() {
+        let pattern = Pattern::from_byte_pattern("AA").unwrap();
+        let slice: &[u8] = &[0xAA; 100];
+
+        let results = scanner::threaded::scan_all(slice, &pattern, MatchMode::Overlapping, Some(7))
+            .unwrap();
+        assert_eq!(results.iter().map(|r| r.location).collect::<Vec<_>>(), (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn scan_pool_can_be_reused_across_scans() {
+        let randomness = include_bytes!("../../test/random.bin");
+        let pool = ScanPool::new(3).unwrap();
+        let pattern = Pattern::from_byte_pattern("B7 [?? CF D8 ??] 0A ?? 27").unwrap();
+
+        for _ in 0..4 {
+            let result = pool.scan(randomness, &pattern).unwrap().unwrap();
+            assert_eq!(result.location, 867776);
+
+            let results = pool.scan_all(randomness, &pattern, MatchMode::NonOverlapping).unwrap();
+            assert_eq!(results.len(), 1);
+        }
+    }
 }
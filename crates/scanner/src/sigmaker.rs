@@ -0,0 +1,293 @@
+use std::fmt;
+use std::ops::Range;
+use std::error::Error;
+
+use iced_x86::{Decoder, DecoderOptions, Instruction, OpKind};
+
+use crate::pattern::Pattern;
+use crate::scanner::MatchMode;
+use crate::scanner::simple::scan_all;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureOptions {
+    /// Bitness of the code, 16, 32 or 64.
+    pub bitness: u32,
+    /// Amount of bytes the signature is allowed to grow to.
+    pub max_length: usize,
+    /// Bytes to capture, relative to the address the signature is made for.
+    pub capture: Option<Range<usize>>,
+    /// Immediates with an absolute value of at least this are wildcarded, as they're likely to be
+    /// addresses or sizes that change between builds.
+    pub immediate_threshold: u64,
+}
+
+impl Default for SignatureOptions {
+    fn default() -> Self {
+        Self {
+            bitness: 64,
+            max_length: 128,
+            capture: None,
+            immediate_threshold: 0x10000,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SignatureError {
+    /// The address doesn't point into the region.
+    AddressOutOfRange,
+    /// The capture extends past the instructions that could be decoded or past `max_length`.
+    CaptureOutOfRange,
+    /// No valid instruction could be decoded at the address.
+    InvalidInstruction,
+    /// Even the longest signature matches more than once, the amount of matches is attached.
+    NotUnique(usize),
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AddressOutOfRange => write!(f, "address is outside of the region"),
+            Self::CaptureOutOfRange => write!(f, "capture extends past the decoded instructions"),
+            Self::InvalidInstruction => write!(f, "no valid instruction at the address"),
+            Self::NotUnique(count) => write!(f, "longest signature still matches {count} times"),
+        }
+    }
+}
+
+impl Error for SignatureError {}
+
+/// Creates the shortest signature for `address` that matches exactly once in `region`. Instructions
+/// are decoded from the address onwards and operands that are likely to change between builds are
+/// wildcarded: displacements, which includes RIP-relative offsets, branch targets and large
+/// immediates. `base` is the address of the first byte of the region.
+pub fn make_signature(
+    region: &[u8],
+    base: usize,
+    address: usize,
+    options: &SignatureOptions,
+) -> Result<Pattern, SignatureError> {
+    let offset = address.checked_sub(base)
+        .filter(|o| *o < region.len())
+        .ok_or(SignatureError::AddressOutOfRange)?;
+
+    let (bytes, mask) = decode_masked(region, base, offset, options);
+    if bytes.is_empty() {
+        return Err(SignatureError::InvalidInstruction);
+    }
+
+    let minimum = match &options.capture {
+        Some(capture) if capture.start > capture.end || capture.end > bytes.len() => {
+            return Err(SignatureError::CaptureOutOfRange);
+        },
+        Some(capture) => capture.end.max(1),
+        None => 1,
+    };
+
+    let count = count_matches(region, &build_pattern(&bytes, &mask, minimum, options));
+    if count == 1 {
+        return Ok(build_pattern(&bytes, &mask, minimum, options));
+    }
+
+    let count = count_matches(region, &build_pattern(&bytes, &mask, bytes.len(), options));
+    if count != 1 {
+        return Err(SignatureError::NotUnique(count));
+    }
+
+    // Making a signature longer never introduces new matches, so the shortest unique length can be
+    // searched for. `low` is known to match more than once, `high` exactly once.
+    let (mut low, mut high) = (minimum, bytes.len());
+    while high - low > 1 {
+        let length = low + (high - low) / 2;
+        match count_matches(region, &build_pattern(&bytes, &mask, length, options)) {
+            1 => high = length,
+            _ => low = length,
+        }
+    }
+
+    Ok(build_pattern(&bytes, &mask, high, options))
+}
+
+/// Decodes instructions at `offset` until `max_length` bytes are covered or the decoder runs into
+/// something invalid. Returns the bytes of the instructions and their masks.
+fn decode_masked(
+    region: &[u8],
+    base: usize,
+    offset: usize,
+    options: &SignatureOptions,
+) -> (Vec<u8>, Vec<u8>) {
+    let mut decoder = Decoder::with_ip(
+        options.bitness,
+        &region[offset..],
+        (base + offset) as u64,
+        DecoderOptions::NONE,
+    );
+
+    let mut bytes = Vec::new();
+    let mut mask = Vec::new();
+    let mut instruction = Instruction::default();
+    while decoder.can_decode() && bytes.len() < options.max_length {
+        let start = decoder.position();
+        decoder.decode_out(&mut instruction);
+        if instruction.is_invalid() {
+            break;
+        }
+
+        let instruction_bytes = &region[offset + start..offset + start + instruction.len()];
+        let mut instruction_mask = vec![0xFF; instruction.len()];
+        let mut wildcard = |range: Range<usize>| instruction_mask[range].fill(0x00);
+
+        let offsets = decoder.get_constant_offsets(&instruction);
+        if offsets.has_displacement() {
+            let displacement = offsets.displacement_offset();
+            wildcard(displacement..displacement + offsets.displacement_size());
+        }
+
+        let is_branch = (0..instruction.op_count()).any(|i| is_branch_operand(instruction.op_kind(i)));
+        for (immediate, size) in [
+            (offsets.immediate_offset(), offsets.immediate_size()),
+            (offsets.immediate_offset2(), offsets.immediate_size2()),
+        ] {
+            if size == 0 {
+                continue;
+            }
+
+            let value = read_signed(&instruction_bytes[immediate..immediate + size]);
+            if is_branch || value.unsigned_abs() >= options.immediate_threshold {
+                wildcard(immediate..immediate + size);
+            }
+        }
+
+        bytes.extend(instruction_bytes.iter().zip(instruction_mask.iter()).map(|(b, m)| b & m));
+        mask.extend(instruction_mask);
+    }
+
+    bytes.truncate(options.max_length);
+    mask.truncate(options.max_length);
+    (bytes, mask)
+}
+
+fn is_branch_operand(kind: OpKind) -> bool {
+    matches!(
+        kind,
+        OpKind::NearBranch16
+            | OpKind::NearBranch32
+            | OpKind::NearBranch64
+            | OpKind::FarBranch16
+            | OpKind::FarBranch32
+    )
+}
+
+/// Reads a little-endian value of up to 8 bytes and sign-extends it.
+fn read_signed(bytes: &[u8]) -> i64 {
+    let mut buffer = [0u8; 8];
+    buffer[..bytes.len()].copy_from_slice(bytes);
+
+    let shift = 64 - bytes.len() * 8;
+    (i64::from_le_bytes(buffer) << shift) >> shift
+}
+
+fn build_pattern(bytes: &[u8], mask: &[u8], length: usize, options: &SignatureOptions) -> Pattern {
+    let mut pattern = Pattern::from_byte_slice(&bytes[..length]);
+    pattern.mask = mask[..length].to_vec();
+
+    if let Some(capture) = &options.capture {
+        pattern.capture_groups.push(capture.clone());
+        pattern.capture_names.push(None);
+    }
+
+    pattern
+}
+
+fn count_matches(region: &[u8], pattern: &Pattern) -> usize {
+    scan_all(region, pattern, MatchMode::Overlapping).len()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pattern::Pattern;
+    use crate::scanner::MatchMode;
+    use crate::scanner::simple::scan_all;
+    use crate::sigmaker::{make_signature, SignatureError, SignatureOptions};
+    use crate::pe::fixtures::{Code, DATA_BASE, TEXT_BASE};
+
+    /// A handful of functions that load a global, call something and test the result. Only the
+    /// registers, the test and the operands tell them apart.
+    fn fixture() -> Vec<u8> {
+        // The mov register and the test opcode of every function
+        let functions = [(0x0D, 0x84), (0x0D, 0x84), (0x15, 0x84), (0x0D, 0x85)];
+
+        functions.iter().enumerate()
+            .fold(Code::new(TEXT_BASE), |code, (i, (register, test))| {
+                // sub rsp, 28h; mov rcx|rdx, [global]; call callee; test al, al|eax, eax; ret
+                code.at(i * 32)
+                    .bytes(&[0x48, 0x83, 0xEC, 0x28])
+                    .rip_relative(&[0x48, 0x8B, *register], DATA_BASE + i * 8, &[])
+                    .call(TEXT_BASE + 0x1000 + i * 0x100)
+                    .bytes(&[*test, 0xC0, 0xC3])
+            })
+            .at(128)
+            .into_bytes()
+    }
+
+    #[test]
+    fn signature_wildcards_relocatable_operands() {
+        let region = fixture();
+
+        // The third function differs from the others at the register of the mov
+        let pattern = make_signature(&region, TEXT_BASE, TEXT_BASE + 64, &SignatureOptions::default()).unwrap();
+        assert_eq!(pattern.to_string(), "48 83 EC 28 48 8B 15");
+
+        // The fourth function only differs at the test, everything before it is wildcarded
+        let pattern = make_signature(&region, TEXT_BASE, TEXT_BASE + 96, &SignatureOptions::default()).unwrap();
+        assert_eq!(pattern.to_string(), "48 83 EC 28 48 8B 0D ?? ?? ?? ?? E8 ?? ?? ?? ?? 85");
+    }
+
+    #[test]
+    fn signature_is_unique_and_captures() {
+        let region = fixture();
+        let options = SignatureOptions { capture: Some(7..11), ..Default::default() };
+
+        let pattern = make_signature(&region, TEXT_BASE, TEXT_BASE + 96, &options).unwrap();
+        let results = scan_all(&region, &pattern, MatchMode::Overlapping);
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].location, 96);
+        // The displacement of the mov, it refers to the fourth global
+        let displacement = results[0].captures[0].as_i32().unwrap();
+        assert_eq!(TEXT_BASE + 96 + 11 + displacement as usize, DATA_BASE + 24);
+    }
+
+    #[test]
+    fn signature_keeps_small_immediates() {
+        // mov eax, 10h; ret; mov eax, 12345678h; ret
+        let region = [0xB8, 0x10, 0x00, 0x00, 0x00, 0xC3, 0xB8, 0x78, 0x56, 0x34, 0x12, 0xC3];
+        let options = SignatureOptions { max_length: 6, ..Default::default() };
+
+        let pattern = make_signature(&region, TEXT_BASE, TEXT_BASE, &options).unwrap();
+        assert_eq!(pattern, Pattern::from_byte_pattern("B8 10").unwrap());
+
+        let pattern = make_signature(&region, TEXT_BASE, TEXT_BASE + 6, &options);
+        assert_eq!(pattern, Err(SignatureError::NotUnique(2)));
+    }
+
+    #[test]
+    fn signature_reports_bad_input() {
+        let region = fixture();
+
+        assert_eq!(
+            make_signature(&region, TEXT_BASE, TEXT_BASE - 1, &SignatureOptions::default()),
+            Err(SignatureError::AddressOutOfRange),
+        );
+        assert_eq!(
+            make_signature(&[0x06], TEXT_BASE, TEXT_BASE, &SignatureOptions::default()),
+            Err(SignatureError::InvalidInstruction),
+        );
+
+        let options = SignatureOptions { capture: Some(0..200), ..Default::default() };
+        assert_eq!(
+            make_signature(&region, TEXT_BASE, TEXT_BASE, &options),
+            Err(SignatureError::CaptureOutOfRange),
+        );
+    }
+}
@@ -0,0 +1,110 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringEncoding {
+    Ascii,
+    /// UTF-16LE.
+    Utf16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundString {
+    /// Offset of the first byte of the string in the region.
+    pub offset: usize,
+    pub encoding: StringEncoding,
+    pub value: String,
+}
+
+impl FoundString {
+    /// Amount of bytes the string takes up in the region.
+    pub fn byte_length(&self) -> usize {
+        match self.encoding {
+            StringEncoding::Ascii => self.value.len(),
+            StringEncoding::Utf16 => self.value.len() * 2,
+        }
+    }
+}
+
+/// Extracts runs of at least `min_length` printable ASCII characters from the region, both as
+/// single bytes and as UTF-16LE code units. Strings don't have to be null-terminated. UTF-16
+/// strings are looked for at both even and odd offsets. The strings are sorted by offset.
+pub fn find_strings(region: &[u8], min_length: usize) -> Vec<FoundString> {
+    let min_length = min_length.max(1);
+    let mut strings = Vec::new();
+
+    collect_runs(region.iter().copied(), min_length, |start, value| {
+        strings.push(FoundString { offset: start, encoding: StringEncoding::Ascii, value });
+    });
+
+    for alignment in 0..2 {
+        let units = region.get(alignment..).unwrap_or_default()
+            .chunks_exact(2)
+            .map(|unit| match unit[1] {
+                0 => unit[0],
+                // Never printable, ends the run
+                _ => 0,
+            });
+
+        collect_runs(units, min_length, |start, value| {
+            strings.push(FoundString { offset: alignment + start * 2, encoding: StringEncoding::Utf16, value });
+        });
+    }
+
+    strings.sort_by_key(|s| s.offset);
+    strings
+}
+
+/// Calls `on_run` with the index and contents of every run of printable characters that's long
+/// enough.
+fn collect_runs(characters: impl Iterator<Item = u8>, min_length: usize, mut on_run: impl FnMut(usize, String)) {
+    let mut run = String::new();
+    let mut start = 0;
+
+    for (index, character) in characters.chain([0]).enumerate() {
+        if is_printable(character) {
+            if run.is_empty() {
+                start = index;
+            }
+
+            run.push(character as char);
+            continue;
+        }
+
+        if run.len() >= min_length {
+            on_run(start, std::mem::take(&mut run));
+        }
+
+        run.clear();
+    }
+}
+
+fn is_printable(character: u8) -> bool {
+    character.is_ascii_graphic() || character == b' ' || character == b'\t'
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::strings::{find_strings, FoundString, StringEncoding};
+
+    #[test]
+    fn strings_are_found_in_both_encodings() {
+        let mut region = vec![0xFFu8; 3];
+        region.extend(b"CSGparam\0ab\0\0");
+        region.extend("Debug menu".encode_utf16().flat_map(u16::to_le_bytes));
+        region.extend([0x00, 0x00, 0x01]);
+        region.extend("odd".encode_utf16().flat_map(u16::to_le_bytes));
+
+        let strings = find_strings(&region, 3);
+        assert_eq!(strings, vec![
+            FoundString { offset: 3, encoding: StringEncoding::Ascii, value: "CSGparam".to_string() },
+            FoundString { offset: 16, encoding: StringEncoding::Utf16, value: "Debug menu".to_string() },
+            FoundString { offset: 39, encoding: StringEncoding::Utf16, value: "odd".to_string() },
+        ]);
+
+        assert_eq!(strings[1].byte_length(), 20);
+        assert_eq!(&region[16..36], "Debug menu".encode_utf16().flat_map(u16::to_le_bytes).collect::<Vec<_>>());
+
+        // Shorter strings show up once the minimum allows for them
+        assert!(find_strings(&region, 1).iter().any(|s| s.value == "ab"));
+        assert_eq!(find_strings(&region, 9).len(), 1);
+        assert_eq!(find_strings(&[], 1), vec![]);
+    }
+}
@@ -0,0 +1,198 @@
+use iced_x86::{Decoder, DecoderOptions};
+
+use crate::pattern::Pattern;
+use crate::scanner::MatchMode;
+use crate::scanner::simple::scan_all;
+
+/// Longest an x86 instruction can be.
+const MAX_INSTRUCTION_LENGTH: usize = 15;
+
+/// Bytes to look for references in, along with the address of the first byte.
+#[derive(Debug, Clone, Copy)]
+pub struct XrefRegion<'a> {
+    pub base: usize,
+    pub bytes: &'a [u8],
+    /// Code regions are also searched for RIP-relative operands, calls and jumps.
+    pub code: bool,
+}
+
+impl<'a> XrefRegion<'a> {
+    /// A region with code, like `.text`.
+    pub fn code(base: usize, bytes: &'a [u8]) -> Self {
+        Self { base, bytes, code: true }
+    }
+
+    /// A region with data, like `.data` or `.rdata`. Only searched for absolute pointers.
+    pub fn data(base: usize, bytes: &'a [u8]) -> Self {
+        Self { base, bytes, code: false }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XrefKind {
+    /// An 8-byte pointer to the target.
+    AbsolutePointer,
+    /// A `disp32` operand that resolves to the target, relative to the end of its instruction.
+    RipRelative { instruction_start: usize },
+    /// An `E8` call with a `rel32` to the target.
+    Call,
+    /// An `E9` jump with a `rel32` to the target.
+    Jump,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Xref {
+    /// Address of the reference: the pointer, the displacement or the call or jump instruction.
+    pub address: usize,
+    pub kind: XrefKind,
+}
+
+/// Finds every reference to `target` in the regions, sorted by address.
+///
+/// RIP-relative operands are found without decoding the entire region. Every displacement that
+/// resolves to the target, given an immediate of 0, 1, 2 or 4 bytes after it, is decoded from the
+/// instruction starts in front of it. The longest instruction that has the displacement at that
+/// position and refers to the target is taken.
+pub fn find_xrefs(target: usize, regions: &[XrefRegion]) -> Vec<Xref> {
+    let mut xrefs = Vec::new();
+
+    for region in regions.iter() {
+        find_pointers(target, region, &mut xrefs);
+
+        if region.code {
+            find_branches(target, region, &mut xrefs);
+            find_rip_relative(target, region, &mut xrefs);
+        }
+    }
+
+    xrefs.sort_by_key(|x| x.address);
+    xrefs
+}
+
+fn find_pointers(target: usize, region: &XrefRegion, xrefs: &mut Vec<Xref>) {
+    let pattern = Pattern::from_byte_slice(&(target as u64).to_le_bytes());
+
+    xrefs.extend(
+        scan_all(region.bytes, &pattern, MatchMode::Overlapping)
+            .into_iter()
+            .map(|r| Xref { address: region.base + r.location, kind: XrefKind::AbsolutePointer })
+    );
+}
+
+fn find_branches(target: usize, region: &XrefRegion, xrefs: &mut Vec<Xref>) {
+    let pattern = Pattern::from_byte_pattern("(E8|E9) [?? ?? ?? ??]").unwrap();
+
+    for result in scan_all(region.bytes, &pattern, MatchMode::Overlapping) {
+        let address = region.base + result.location;
+        let relative = result.captures[0].as_i32().unwrap();
+
+        if (address + 5).wrapping_add_signed(relative as isize) != target {
+            continue;
+        }
+
+        let kind = match region.bytes[result.location] {
+            0xE8 => XrefKind::Call,
+            _ => XrefKind::Jump,
+        };
+
+        xrefs.push(Xref { address, kind });
+    }
+}
+
+fn find_rip_relative(target: usize, region: &XrefRegion, xrefs: &mut Vec<Xref>) {
+    for (offset, displacement) in region.bytes.windows(4).enumerate() {
+        let displacement = i32::from_le_bytes(displacement.try_into().unwrap()) as isize;
+
+        // The instruction has to end right after the displacement or after its immediate
+        let end = target.wrapping_add_signed(-displacement);
+        let immediate = end.wrapping_sub(region.base + offset + 4);
+        if !matches!(immediate, 0 | 1 | 2 | 4) {
+            continue;
+        }
+
+        if let Some(instruction_start) = find_instruction_start(target, region, offset) {
+            xrefs.push(Xref {
+                address: region.base + offset,
+                kind: XrefKind::RipRelative { instruction_start },
+            });
+        }
+    }
+}
+
+/// Decodes from every position the instruction could start at, longest instruction first, and
+/// returns the first one that has its RIP-relative displacement at `displacement`.
+fn find_instruction_start(target: usize, region: &XrefRegion, displacement: usize) -> Option<usize> {
+    (1..=MAX_INSTRUCTION_LENGTH.min(displacement))
+        .rev()
+        .map(|length| displacement - length)
+        .find(|start| {
+            let end = (start + MAX_INSTRUCTION_LENGTH).min(region.bytes.len());
+            let mut decoder = Decoder::with_ip(
+                64,
+                &region.bytes[*start..end],
+                (region.base + start) as u64,
+                DecoderOptions::NONE,
+            );
+
+            let instruction = decoder.decode();
+            !instruction.is_invalid()
+                && instruction.is_ip_rel_memory_operand()
+                && instruction.ip_rel_memory_address() == target as u64
+                && start + decoder.get_constant_offsets(&instruction).displacement_offset() == displacement
+        })
+        .map(|start| region.base + start)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pe::fixtures::{Code, DATA_BASE, TEXT_BASE};
+    use crate::xref::{find_xrefs, Xref, XrefKind, XrefRegion};
+
+    const TARGET: usize = DATA_BASE + 0x10;
+
+    fn text() -> Vec<u8> {
+        Code::new(TEXT_BASE)
+            // mov rax, [target]
+            .rip_relative(&[0x48, 0x8B, 0x05], TARGET, &[])
+            // cmp dword [target], 5, there's an immediate after the displacement
+            .at(0x20)
+            .rip_relative(&[0x83, 0x3D], TARGET, &[0x05])
+            .at(0x30)
+            .call(TARGET)
+            .at(0x40)
+            .jmp(TARGET)
+            // lea rcx, [target+1] points one byte past the target
+            .at(0x50)
+            .rip_relative(&[0x48, 0x8D, 0x0D], TARGET + 1, &[])
+            .at(0x60)
+            .into_bytes()
+    }
+
+    #[test]
+    fn xrefs_are_found_in_code_and_data() {
+        let text = text();
+        let mut data = vec![0u8; 0x40];
+        data[0x08..0x10].copy_from_slice(&(TARGET as u64).to_le_bytes());
+        data[0x23..0x2B].copy_from_slice(&(TARGET as u64).to_le_bytes());
+
+        let xrefs = find_xrefs(TARGET, &[XrefRegion::code(TEXT_BASE, &text), XrefRegion::data(DATA_BASE, &data)]);
+        assert_eq!(xrefs, vec![
+            Xref { address: TEXT_BASE + 0x03, kind: XrefKind::RipRelative { instruction_start: TEXT_BASE } },
+            Xref { address: TEXT_BASE + 0x22, kind: XrefKind::RipRelative { instruction_start: TEXT_BASE + 0x20 } },
+            Xref { address: TEXT_BASE + 0x30, kind: XrefKind::Call },
+            Xref { address: TEXT_BASE + 0x40, kind: XrefKind::Jump },
+            Xref { address: DATA_BASE + 0x08, kind: XrefKind::AbsolutePointer },
+            Xref { address: DATA_BASE + 0x23, kind: XrefKind::AbsolutePointer },
+        ]);
+    }
+
+    #[test]
+    fn xrefs_in_data_regions_are_pointers_only() {
+        let text = text();
+
+        assert_eq!(find_xrefs(TARGET, &[XrefRegion::data(TEXT_BASE, &text)]), vec![]);
+        assert_eq!(find_xrefs(TARGET + 1, &[XrefRegion::code(TEXT_BASE, &text)]), vec![
+            Xref { address: TEXT_BASE + 0x53, kind: XrefKind::RipRelative { instruction_start: TEXT_BASE + 0x50 } },
+        ]);
+    }
+}
@@ -0,0 +1,166 @@
+use quote::quote;
+use proc_macro2::{Span, TokenStream};
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, LitStr, Path, Token};
+
+use broadsword_scanner::pattern::Pattern;
+
+/// Parses a byte pattern at compile time and expands to the `Pattern`. Invalid patterns result in
+/// a compile error instead of a panic when the pattern is first used.
+///
+/// ```
+/// use broadsword_scanner_macros::pattern;
+///
+/// let pattern = pattern!("48 8B 05 [?? ?? ?? ??]");
+/// ```
+///
+/// The error message holds the pattern with a caret pointing at the offending character. The error
+/// itself is placed on that character where the compiler supports spans inside of literals, which
+/// stable Rust doesn't. There it falls back to the whole literal.
+///
+/// ```compile_fail
+/// use broadsword_scanner_macros::pattern;
+///
+/// let pattern = pattern!("48 8B 05 [?? ?? ?? ??");
+/// ```
+///
+/// The expansion refers to `::broadsword_scanner::pattern`. Crates re-exporting the scanner can
+/// pass the module that holds `Pattern`, `Jump` and `Alternatives` as a first argument instead:
+/// `pattern!(broadsword::scanner, "48 8B 05")`.
+#[proc_macro]
+pub fn pattern(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as PatternInput);
+
+    expand_input(&input).into()
+}
+
+/// Expands to the pattern or to a `compile_error!` with the rendered parser error.
+fn expand_input(input: &PatternInput) -> TokenStream {
+    match Pattern::from_byte_pattern(&input.pattern.value()) {
+        Ok(pattern) => expand(&input.path, &pattern),
+        Err(e) => {
+            let span = e.span()
+                .and_then(|s| literal_subspan(&input.pattern, s.offset))
+                .unwrap_or_else(|| input.pattern.span());
+
+            syn::Error::new(span, e.render(&input.pattern.value()))
+                .to_compile_error()
+        },
+    }
+}
+
+struct PatternInput {
+    path: Path,
+    pattern: LitStr,
+}
+
+impl Parse for PatternInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(LitStr) {
+            return Ok(Self {
+                path: syn::parse_quote!(::broadsword_scanner::pattern),
+                pattern: input.parse()?,
+            });
+        }
+
+        let path = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let pattern = input.parse()?;
+
+        Ok(Self { path, pattern })
+    }
+}
+
+/// Builds the `Pattern` out of statics so nothing is parsed at runtime.
+fn expand(path: &Path, pattern: &Pattern) -> TokenStream {
+    let length = pattern.length;
+    let bytes = &pattern.bytes;
+    let mask = &pattern.mask;
+
+    let capture_groups = pattern.capture_groups.iter()
+        .map(|g| {
+            let (start, end) = (g.start, g.end);
+            quote! { #start..#end }
+        });
+
+    let capture_names = pattern.capture_names.iter()
+        .map(|n| match n {
+            Some(name) => quote! { Some(String::from(#name)) },
+            None => quote! { None },
+        });
+
+    let jumps = pattern.jumps.iter()
+        .map(|j| {
+            let (position, min, max) = (j.position, j.min, j.max);
+            quote! { #path::Jump { position: #position, min: #min, max: #max } }
+        });
+
+    let alternatives = pattern.alternatives.iter()
+        .map(|a| {
+            let position = a.position;
+            let values = a.values.iter().map(|(b, m)| quote! { (#b, #m) });
+            quote! { #path::Alternatives { position: #position, values: vec![#(#values),*] } }
+        });
+
+    quote! {
+        {
+            static BYTES: [u8; #length] = [#(#bytes),*];
+            static MASK: [u8; #length] = [#(#mask),*];
+
+            #path::Pattern {
+                bytes: BYTES.to_vec(),
+                mask: MASK.to_vec(),
+                length: #length,
+                capture_groups: vec![#(#capture_groups),*],
+                capture_names: vec![#(#capture_names),*],
+                jumps: vec![#(#jumps),*],
+                alternatives: vec![#(#alternatives),*],
+            }
+        }
+    }
+}
+
+/// Span of the character at `offset` in the string literal. Only works for literals without escapes
+/// and on compilers that support subspans, the caller falls back to the span of the whole literal.
+/// `Literal::subspan` always returns `None` on stable, so there it's always the whole literal.
+fn literal_subspan(literal: &LitStr, offset: usize) -> Option<Span> {
+    let source = literal.token().to_string();
+    let value = literal.value();
+
+    // Raw strings start with r, any amount of # and the quote
+    let start = source.find('"')? + 1;
+    if source.get(start..start + value.len())? != value {
+        return None;
+    }
+
+    let character_length = value[offset..].chars().next().map_or(0, char::len_utf8);
+    literal.token().subspan(start + offset..start + offset + character_length)
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::quote;
+
+    use crate::{expand_input, PatternInput};
+
+    fn expand(input: proc_macro2::TokenStream) -> String {
+        expand_input(&syn::parse2::<PatternInput>(input).unwrap()).to_string()
+    }
+
+    #[test]
+    fn pattern_macro_renders_errors_with_a_caret() {
+        let expanded = expand(quote! { "48 8B 05 [?? ?? ?? ??" });
+
+        assert!(expanded.starts_with(":: core :: compile_error !"), "{expanded}");
+        let message = r#"capture group opened at offset 9 ('[') is never closed\n48 8B 05 [?? ?? ?? ??\n         ^"#;
+        assert!(expanded.contains(message), "{expanded}");
+    }
+
+    #[test]
+    fn pattern_macro_expands_valid_patterns() {
+        let expanded = expand(quote! { broadsword::scanner, "48 8B 05" });
+
+        assert!(expanded.contains("broadsword :: scanner :: Pattern"), "{expanded}");
+        assert!(!expanded.contains("compile_error"), "{expanded}");
+    }
+}
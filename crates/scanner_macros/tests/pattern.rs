@@ -0,0 +1,23 @@
+use broadsword_scanner::pattern::Pattern;
+use broadsword_scanner_macros::pattern;
+
+#[test]
+fn pattern_macro_matches_runtime_parsing() {
+    let inputs = [
+        ("48 8B 05 [?? ?? ?? ??]", pattern!("48 8B 05 [?? ?? ?? ??]")),
+        ("48 8b 0d [vtable: ?? ?? ?? ??] e8", pattern!("48 8b 0d [vtable: ?? ?? ?? ??] e8")),
+        ("E8 {4-16} (E8|E9|4?) ?F m0101?..?", pattern!("E8 {4-16} (E8|E9|4?) ?F m0101?..?")),
+        ("48 [] 8B", pattern!(r"48 [] 8B")),
+    ];
+
+    for (input, pattern) in inputs {
+        assert_eq!(pattern, Pattern::from_byte_pattern(input).unwrap(), "{input}");
+    }
+}
+
+#[test]
+fn pattern_macro_accepts_module_path() {
+    let pattern = pattern!(broadsword_scanner::pattern, "48 8B 05 [?? ?? ?? ??]");
+
+    assert_eq!(pattern, Pattern::from_byte_pattern("48 8B 05 [?? ?? ?? ??]").unwrap());
+}
@@ -3,6 +3,8 @@ pub use broadsword_microsoft_runtime::module::get_module_handle;
 pub use broadsword_microsoft_runtime::module::get_module_symbol;
 pub use broadsword_microsoft_runtime::module::get_module_pointer_belongs_to;
 pub use broadsword_microsoft_runtime::module::get_module_section_range;
+pub use broadsword_microsoft_runtime::module::get_module_sections;
+pub use broadsword_microsoft_runtime::module::Section;
 
 pub use broadsword_microsoft_runtime::rtti::get_classname as get_rtti_classname;
 pub use broadsword_microsoft_runtime::rtti::get_instance_classname as get_rtti_instance_classname;
@@ -1,4 +1,32 @@
 pub use broadsword_scanner::scanner::*;
 pub use broadsword_scanner::pattern::parser::*;
 pub use broadsword_scanner::pattern::tokenizer::*;
-pub use broadsword_scanner::pattern::Pattern;
+pub use broadsword_scanner::pattern::{Alternatives, BuilderError, CodeStyleError, ExportError, Jump, Pattern, PatternBuilder};
+pub use broadsword_scanner::pattern::PatternSet;
+pub use crate::pattern;
+pub use broadsword_scanner::function;
+pub use broadsword_scanner::strings;
+
+#[cfg(feature = "sigmaker")]
+pub use broadsword_scanner::sigmaker;
+#[cfg(feature = "instructions")]
+pub use broadsword_scanner::instruction;
+#[cfg(feature = "xref")]
+pub use broadsword_scanner::xref;
+#[cfg(feature = "module")]
+pub use broadsword_scanner::module;
+#[cfg(feature = "database")]
+pub use broadsword_scanner::database;
+#[cfg(feature = "cache")]
+pub use broadsword_scanner::cache;
+
+#[doc(hidden)]
+pub use broadsword_scanner_macros::pattern as pattern_with_path;
+
+/// Parses a byte pattern at compile time, see `broadsword_scanner_macros::pattern`.
+#[macro_export]
+macro_rules! pattern {
+    ($pattern:literal) => {
+        $crate::scanner::pattern_with_path!($crate::scanner, $pattern)
+    };
+}